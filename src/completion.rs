@@ -57,7 +57,7 @@ fn run_gen_completion(args: &ArgMatches) {
             let mut output_file = match File::create(f) {
                 Ok(file) => file,
                 Err(e) => {
-                    error!("{}: {}", f, e.to_string());
+                    error!("{}: {}", f, e);
                     return;
                 }
             };
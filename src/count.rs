@@ -72,8 +72,8 @@ pub fn count_lines(buf: &[u8], eol: u8) -> u64 {
     if len >= 2 * USIZE_BYTES {
         while i <= len - (2 * USIZE_BYTES) {
             unsafe {
-                let u = *(ptr.offset(i as isize) as *const usize);
-                let v = *(ptr.offset((i + USIZE_BYTES) as isize) as *const usize);
+                let u = *(ptr.add(i) as *const usize);
+                let v = *(ptr.add(i + USIZE_BYTES) as *const usize);
 
                 count += count_eol(u ^ repeated_eol);
                 count += count_eol(v ^ repeated_eol);
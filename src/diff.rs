@@ -1,6 +1,73 @@
+pub mod algorithm;
+mod bytes;
+mod dir;
+mod encoding;
+mod funcname;
+mod histogram;
+mod html;
+mod hunk;
+mod inline;
+pub mod input;
+mod interactive;
+mod json;
+mod moved;
+mod myers;
+mod normalize;
+pub mod ops;
+mod patience;
+mod rename;
+mod set;
+mod side_by_side;
+mod stat;
+mod structural;
+mod syntax;
+mod table;
+mod unified;
+mod words;
+
 use super::utils::UtilSubCommand;
-use clap::{App, Arg, ArgMatches, SubCommand};
-use log::warn;
+use crate::walk::{SearchFilter, Walk};
+use algorithm::Algorithm;
+use ansi_term::{Color::*, Style};
+use bytes::HexPrinter;
+use clap::{App, Arg, ArgGroup, ArgMatches, SubCommand};
+use dir::Change;
+use encoding::Findings;
+use funcname::FunctionMatcher;
+use html::{HtmlView, HtmlWriter};
+use hunk::{group_hunks, Hunk, DEFAULT_CONTEXT};
+use ignore::overrides::OverrideBuilder;
+use input::{format_size, line_ids, parse_size, split_lines, Input};
+use interactive::Review;
+use json::{FileDiff, JsonWriter};
+use log::{error, warn};
+use moved::{find_moved_blocks, MovedLines};
+use normalize::Normalizer;
+use ops::common_prefix_len;
+use ops::DiffOp;
+use regex::Regex;
+use rename::{Rename, RenameKind};
+use set::{Group, LineSets, SetPrinter};
+use side_by_side::SideBySidePrinter;
+use stat::{write_numstat, FileStat, StatFormat, StatPrinter};
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::fs;
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use syntax::SyntaxColors;
+use table::Table;
+use unified::UnifiedPrinter;
+use words::WordPrinter;
+
+/// The exit code if the inputs are the same, like diff(1)
+const EXIT_SAME: i32 = 0;
+
+/// The exit code if the inputs differ
+const EXIT_DIFFER: i32 = 1;
+
+/// The exit code if an input could not be compared
+pub const EXIT_TROUBLE: i32 = 2;
 
 pub struct DiffCommand;
 
@@ -13,7 +80,7 @@ impl UtilSubCommand for DiffCommand {
                 Arg::with_name("bytes")
                     .long("bytes")
                     .short("b")
-                    .conflicts_with_all(&["lines", "chars"])
+                    .conflicts_with_all(&["lines", "chars", "words"])
                     .help("Show difference in bytes"),
             )
             .arg(
@@ -28,25 +95,566 @@ impl UtilSubCommand for DiffCommand {
                     .short("c")
                     .help("Show difference in chars (default)"),
             )
+            .arg(
+                Arg::with_name("words")
+                    .long("words")
+                    .conflicts_with_all(&["lines", "chars", "side_by_side"])
+                    .help("Show difference in words, inline as [-old-]{+new+} or in colors"),
+            )
+            .arg(
+                Arg::with_name("structural")
+                    .long("structural")
+                    .conflicts_with("bytes")
+                    .help("Compare JSON, TOML and YAML documents as data trees, ignoring key order and formatting"),
+            )
+            .arg(
+                Arg::with_name("csv")
+                    .long("csv")
+                    .conflicts_with_all(&["bytes", "structural"])
+                    .help("Compare CSV tables row by row, rows are matched by their key columns"),
+            )
+            .arg(
+                Arg::with_name("tsv")
+                    .long("tsv")
+                    .conflicts_with_all(&["bytes", "structural"])
+                    .help("Compare TSV tables row by row, rows are matched by their key columns"),
+            )
+            .group(ArgGroup::with_name("table").args(&["csv", "tsv"]))
+            .arg(
+                Arg::with_name("set")
+                    .long("set")
+                    .conflicts_with_all(&[
+                        "bytes", "structural", "csv", "tsv", "side_by_side", "stat", "numstat",
+                        "format",
+                    ])
+                    .help("Compare the lines as sets like comm(1), list the lines only in the left file, only in the right file and in both"),
+            )
+            .arg(
+                Arg::with_name("hide")
+                    .long("hide")
+                    .value_name("GROUP")
+                    .takes_value(true)
+                    .multiple(true)
+                    .number_of_values(1)
+                    .use_delimiter(true)
+                    .possible_values(&["left", "right", "both"])
+                    .requires("set")
+                    .help("Leave out the groups of --set, separated by commas"),
+            )
+            .arg(
+                Arg::with_name("count")
+                    .long("count")
+                    .requires("set")
+                    .help("Count duplicate lines with --set, a line twice on the left and once on the right is once in both and once only on the left"),
+            )
+            .arg(
+                Arg::with_name("key")
+                    .long("key")
+                    .value_name("COLUMN")
+                    .takes_value(true)
+                    .multiple(true)
+                    .number_of_values(1)
+                    .use_delimiter(true)
+                    .requires("table")
+                    .help("The key columns of --csv and --tsv, separated by commas (default the first column)"),
+            )
+            .arg(
+                Arg::with_name("binary")
+                    .long("binary")
+                    .value_name("MODE")
+                    .takes_value(true)
+                    .possible_values(&["report", "auto"])
+                    .help("How binary files are compared: report only that they differ (default), or auto to show a byte diff"),
+            )
+            .arg(
+                Arg::with_name("text")
+                    .long("text")
+                    .conflicts_with("binary")
+                    .help("Compare all files as text, even if they look binary"),
+            )
+            .arg(
+                Arg::with_name("algorithm")
+                    .long("algorithm")
+                    .value_name("ALGORITHM")
+                    .takes_value(true)
+                    .possible_values(Algorithm::NAMES)
+                    .help("The diff algorithm to use (default myers)"),
+            )
+            .arg(
+                Arg::with_name("context")
+                    .long("context")
+                    .short("U")
+                    .value_name("NUM")
+                    .takes_value(true)
+                    .validator(|n| match n.parse::<usize>() {
+                        Ok(_) => Ok(()),
+                        Err(_) => Err(format!("Invalid number of context lines: {}", n)),
+                    })
+                    .help("Number of context lines, or context rows with --bytes (default 3)"),
+            )
+            .arg(
+                Arg::with_name("function_context")
+                    .long("function-context")
+                    .short("W")
+                    .conflicts_with("bytes")
+                    .help("Show the enclosing function or section of each hunk in its header, for Rust, Python, C and Markdown files"),
+            )
+            .arg(
+                Arg::with_name("no_resync")
+                    .long("no-resync")
+                    .requires("bytes")
+                    .help("Compare bytes by position, do not resynchronise after insertions"),
+            )
+            .arg(
+                Arg::with_name("color_moved")
+                    .long("color-moved")
+                    .help("Color blocks which were moved elsewhere differently from added and deleted lines"),
+            )
+            .arg(
+                Arg::with_name("syntax")
+                    .long("syntax")
+                    .conflicts_with_all(&["bytes", "words", "side_by_side"])
+                    .help("Highlight the syntax of the lines by the language of the file extension, off if stdout is not a terminal"),
+            )
+            .arg(
+                Arg::with_name("side_by_side")
+                    .long("side-by-side")
+                    .short("y")
+                    .help("Show the files in two columns, unified output is used if stdout is not a terminal"),
+            )
+            .arg(
+                Arg::with_name("wrap")
+                    .long("wrap")
+                    .requires("side_by_side")
+                    .help("Wrap long lines in side by side output instead of truncating them"),
+            )
+            .arg(
+                Arg::with_name("ignore_all_space")
+                    .long("ignore-all-space")
+                    .short("w")
+                    .help("Ignore all white space"),
+            )
+            .arg(
+                Arg::with_name("ignore_space_change")
+                    .long("ignore-space-change")
+                    .help("Ignore changes in the amount of white space"),
+            )
+            .arg(
+                Arg::with_name("ignore_blank_lines")
+                    .long("ignore-blank-lines")
+                    .short("B")
+                    .help("Ignore changes whose lines are all blank"),
+            )
+            .arg(
+                Arg::with_name("ignore_case")
+                    .long("ignore-case")
+                    .short("i")
+                    .help("Ignore case differences in file contents"),
+            )
+            .arg(
+                Arg::with_name("strip_trailing_cr")
+                    .long("strip-trailing-cr")
+                    .help("Strip trailing carriage return on input"),
+            )
+            .arg(
+                Arg::with_name("ignore_matching_lines")
+                    .long("ignore-matching-lines")
+                    .short("I")
+                    .value_name("REGEX")
+                    .takes_value(true)
+                    .multiple(true)
+                    .number_of_values(1)
+                    .validator(|re| Regex::new(&re).map(|_| ()).map_err(|e| e.to_string()))
+                    .help("Ignore changes whose lines all match REGEX"),
+            )
+            .arg(
+                Arg::with_name("max_memory")
+                    .long("max-memory")
+                    .value_name("SIZE")
+                    .takes_value(true)
+                    .validator(|size| match parse_size(&size) {
+                        Some(_) => Ok(()),
+                        None => Err(format!("Invalid size: {}", size)),
+                    })
+                    .help("Fail if a line diff needs more than SIZE bytes of memory, e.g. 512M or 2G"),
+            )
+            .arg(
+                Arg::with_name("format")
+                    .long("format")
+                    .value_name("FORMAT")
+                    .takes_value(true)
+                    .possible_values(&["text", "json", "html"])
+                    .help("The output format, json prints a versioned document of all hunks, html a self-contained report (default text)"),
+            )
+            .arg(
+                Arg::with_name("stat")
+                    .long("stat")
+                    .conflicts_with("numstat")
+                    .help("Show the number of inserted and deleted lines of each file with a histogram"),
+            )
+            .arg(
+                Arg::with_name("numstat")
+                    .long("numstat")
+                    .help("Show the number of inserted and deleted lines of each file, separated by tabs"),
+            )
+            .arg(
+                Arg::with_name("summary")
+                    .long("summary")
+                    .short("r")
+                    .help("Only list the differing entries when comparing directories"),
+            )
+            .arg(
+                Arg::with_name("interactive")
+                    .long("interactive")
+                    .requires("output")
+                    .conflicts_with_all(&[
+                        "bytes", "structural", "csv", "tsv", "stat", "numstat", "format", "brief",
+                        "quiet",
+                    ])
+                    .help("Review the hunks in the terminal, accept each from the left or the right file or edit it, and write the merged file"),
+            )
+            .arg(
+                Arg::with_name("output")
+                    .long("out")
+                    .short("o")
+                    .value_name("FILE")
+                    .takes_value(true)
+                    .help("The file which --interactive writes the merged result to, or which --format html writes the report to (default stdout)"),
+            )
+            .arg(
+                Arg::with_name("find_renames")
+                    .long("find-renames")
+                    .short("M")
+                    .value_name("N%")
+                    .takes_value(true)
+                    .min_values(0)
+                    .require_equals(true)
+                    .validator(|v| match rename::parse_threshold(&v) {
+                        Some(_) => Ok(()),
+                        None => Err(format!("Invalid similarity {}, expect a percentage like 50%", v)),
+                    })
+                    .help("Detect renamed files when comparing directories, by a similarity of at least N% (default 50%)"),
+            )
+            .arg(
+                Arg::with_name("find_copies")
+                    .long("find-copies")
+                    .short("C")
+                    .value_name("N%")
+                    .takes_value(true)
+                    .min_values(0)
+                    .require_equals(true)
+                    .validator(|v| match rename::parse_threshold(&v) {
+                        Some(_) => Ok(()),
+                        None => Err(format!("Invalid similarity {}, expect a percentage like 50%", v)),
+                    })
+                    .help("Detect copied files as well as renamed files, the sources of copies are modified or renamed files"),
+            )
+            .arg(
+                Arg::with_name("brief")
+                    .long("brief")
+                    .short("q")
+                    .conflicts_with_all(&["quiet", "stat", "numstat", "format"])
+                    .help("Only report whether files differ, stop comparing a file at its first difference"),
+            )
+            .arg(
+                Arg::with_name("quiet")
+                    .long("quiet")
+                    .conflicts_with_all(&["stat", "numstat", "format"])
+                    .help("Print nothing and stop at the first difference, only the exit code tells the result"),
+            )
+            .arg(
+                Arg::with_name("all")
+                    .long("all")
+                    .short("a")
+                    .help("Compare all entries of directories, including hidden files"),
+            )
+            .arg(
+                Arg::with_name("exclude")
+                    .long("exclude")
+                    .short("x")
+                    .value_name("PATTERN")
+                    .multiple(true)
+                    .number_of_values(1)
+                    .validator(|pattern| {
+                        OverrideBuilder::new(".")
+                            .add(&pattern)
+                            .map(|_| ())
+                            .map_err(|e| e.to_string())
+                    })
+                    .help("Skip the entries of directories which match the glob pattern"),
+            )
             .arg(
                 Arg::with_name("left_file")
                     .value_name("LEFT_FILE")
-                    .help("The left file to diff"),
+                    .help("The left file or directory to diff"),
             )
             .arg(
                 Arg::with_name("right_file")
                     .value_name("RIGHT_FILE")
-                    .help("The right file to diff"),
+                    .help("The right file or directory to diff"),
             )
     }
 
     #[inline]
     fn run(args: &ArgMatches) {
-        run_diff(args);
+        std::process::exit(run_diff(args));
     }
 }
 
-fn run_diff(args: &ArgMatches) {
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DiffMode {
+    Lines,
+    Chars,
+    Words,
+    Bytes,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum OutputFormat {
+    Text,
+    Json,
+    Html,
+}
+
+/// How files are compared which look binary
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BinaryMode {
+    /// Only report that the files differ
+    Report,
+    /// Compare them byte by byte
+    Auto,
+    /// Compare them as text
+    Text,
+}
+
+#[derive(Clone, Debug)]
+struct DiffOption {
+    mode: DiffMode,
+    binary: BinaryMode,
+    algorithm: Algorithm,
+    context: usize,
+    function_context: bool,
+    resync: bool,
+    structural: bool,
+    /// The delimiter of tables which are compared by key columns
+    table: Option<u8>,
+    keys: Vec<String>,
+    /// Compare the lines as sets
+    set: bool,
+    hidden_groups: Vec<Group>,
+    count: bool,
+    color_moved: bool,
+    syntax: bool,
+    side_by_side: bool,
+    wrap: bool,
+    normalizer: Normalizer,
+    max_memory: Option<u64>,
+    stat: Option<StatFormat>,
+    format: OutputFormat,
+    summary: bool,
+    /// The similarity in percent of renamed files, `None` if renames are not detected
+    renames: Option<usize>,
+    copies: bool,
+    /// The output file of the interactive review, `None` if the diff is printed
+    interactive: Option<PathBuf>,
+    output: Option<PathBuf>,
+    /// Only report whether files differ
+    brief: bool,
+    /// Report nothing, stop at the first difference
+    quiet: bool,
+    /// The entries of directories to compare
+    filter: SearchFilter,
+    tty: bool,
+    color: bool,
+}
+
+impl Default for DiffOption {
+    fn default() -> Self {
+        Self {
+            mode: DiffMode::Chars,
+            binary: BinaryMode::Report,
+            algorithm: Algorithm::default(),
+            context: DEFAULT_CONTEXT,
+            function_context: false,
+            resync: true,
+            structural: false,
+            table: None,
+            keys: vec![],
+            set: false,
+            hidden_groups: vec![],
+            count: false,
+            color_moved: false,
+            syntax: false,
+            side_by_side: false,
+            wrap: false,
+            normalizer: Normalizer::new(),
+            max_memory: None,
+            stat: None,
+            format: OutputFormat::Text,
+            summary: false,
+            renames: None,
+            copies: false,
+            interactive: None,
+            output: None,
+            brief: false,
+            quiet: false,
+            filter: SearchFilter::default(),
+            tty: false,
+            color: false,
+        }
+    }
+}
+
+impl DiffOption {
+    fn from_args(args: &ArgMatches) -> Self {
+        let mode = if args.is_present("bytes") {
+            DiffMode::Bytes
+        } else if args.is_present("lines") {
+            DiffMode::Lines
+        } else if args.is_present("words") {
+            DiffMode::Words
+        } else {
+            DiffMode::Chars
+        };
+        let default = Self::default();
+        let tty = io::stdout().is_terminal();
+        let context = args
+            .value_of("context")
+            .and_then(|n| n.parse().ok())
+            .unwrap_or(default.context);
+        let threshold = |name: &str| {
+            args.value_of(name)
+                .and_then(rename::parse_threshold)
+                .unwrap_or(rename::DEFAULT_THRESHOLD)
+        };
+        let renames = if args.is_present("find_copies") {
+            Some(threshold("find_copies"))
+        } else if args.is_present("find_renames") {
+            Some(threshold("find_renames"))
+        } else {
+            None
+        };
+        let binary = if args.is_present("text") {
+            BinaryMode::Text
+        } else if args.value_of("binary") == Some("auto") {
+            BinaryMode::Auto
+        } else {
+            BinaryMode::Report
+        };
+        Self {
+            mode,
+            binary,
+            algorithm: args
+                .value_of("algorithm")
+                .and_then(|a| a.parse().ok())
+                .unwrap_or_default(),
+            context,
+            function_context: args.is_present("function_context"),
+            resync: !args.is_present("no_resync"),
+            structural: args.is_present("structural"),
+            table: if args.is_present("csv") {
+                Some(b',')
+            } else if args.is_present("tsv") {
+                Some(b'\t')
+            } else {
+                None
+            },
+            keys: args
+                .values_of("key")
+                .into_iter()
+                .flatten()
+                .map(str::to_string)
+                .collect(),
+            set: args.is_present("set"),
+            hidden_groups: args
+                .values_of("hide")
+                .into_iter()
+                .flatten()
+                .filter_map(|g| g.parse().ok())
+                .collect(),
+            count: args.is_present("count"),
+            color_moved: args.is_present("color_moved"),
+            syntax: args.is_present("syntax"),
+            side_by_side: args.is_present("side_by_side"),
+            wrap: args.is_present("wrap"),
+            normalizer: Normalizer::new()
+                .ignore_all_space(args.is_present("ignore_all_space"))
+                .ignore_space_change(args.is_present("ignore_space_change"))
+                .ignore_blank_lines(args.is_present("ignore_blank_lines"))
+                .ignore_case(args.is_present("ignore_case"))
+                .strip_trailing_cr(args.is_present("strip_trailing_cr"))
+                .ignore_matching(
+                    args.values_of("ignore_matching_lines")
+                        .into_iter()
+                        .flatten()
+                        .filter_map(|re| Regex::new(re).ok())
+                        .collect(),
+                ),
+            max_memory: args.value_of("max_memory").and_then(parse_size),
+            stat: if args.is_present("stat") {
+                Some(StatFormat::Stat)
+            } else if args.is_present("numstat") {
+                Some(StatFormat::NumStat)
+            } else {
+                None
+            },
+            format: match args.value_of("format") {
+                Some("json") => OutputFormat::Json,
+                Some("html") => OutputFormat::Html,
+                _ => OutputFormat::Text,
+            },
+            summary: args.is_present("summary"),
+            renames,
+            copies: args.is_present("find_copies"),
+            interactive: args
+                .value_of("output")
+                .filter(|_| args.is_present("interactive"))
+                .map(PathBuf::from),
+            output: args.value_of("output").map(PathBuf::from),
+            brief: args.is_present("brief"),
+            quiet: args.is_present("quiet"),
+            filter: args
+                .values_of("exclude")
+                .into_iter()
+                .flatten()
+                .fold(SearchFilter::default(), |filter, pattern| {
+                    filter.exclude_pattern(pattern)
+                })
+                .skip_hidden(!args.is_present("all")),
+            tty,
+            color: tty,
+        }
+    }
+}
+
+impl DiffOption {
+    /// Returns `true` if files only compare equal when their bytes are equal
+    fn is_exact(&self) -> bool {
+        self.normalizer.is_exact() && !self.structural && self.table.is_none() && !self.set
+    }
+}
+
+/// What a comparison found
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+struct Outcome {
+    differ: bool,
+    trouble: bool,
+}
+
+impl Outcome {
+    fn exit_code(self) -> i32 {
+        if self.trouble {
+            EXIT_TROUBLE
+        } else if self.differ {
+            EXIT_DIFFER
+        } else {
+            EXIT_SAME
+        }
+    }
+}
+
+/// Compares the inputs and returns the exit code
+fn run_diff(args: &ArgMatches) -> i32 {
     let left_file = args.value_of("left_file");
     let right_file = args.value_of("right_file");
     if left_file.is_none() {
@@ -55,4 +663,1013 @@ fn run_diff(args: &ArgMatches) {
     if right_file.is_none() {
         warn!("No right file specified");
     }
+    let (left, right) = match (left_file, right_file) {
+        (Some(left), Some(right)) => (left, right),
+        _ => return EXIT_TROUBLE,
+    };
+    let option = DiffOption::from_args(args);
+    match diff_paths(Path::new(left), Path::new(right), &option) {
+        Ok(outcome) => outcome.exit_code(),
+        // The reader stopped reading the printed differences
+        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => EXIT_DIFFER,
+        Err(e) => {
+            error!("{}", e);
+            EXIT_TROUBLE
+        }
+    }
+}
+
+/// The width used when the terminal width is unknown
+const DEFAULT_WIDTH: usize = 80;
+
+/// Returns the width of the terminal which stdout is attached to
+fn terminal_width() -> usize {
+    terminal_size::terminal_size()
+        .map(|(w, _)| w.0 as usize)
+        .or_else(|| std::env::var("COLUMNS").ok()?.parse().ok())
+        .unwrap_or(DEFAULT_WIDTH)
+}
+
+/// Paints the text in the style, or returns it as it is without colors
+#[inline]
+fn paint<'a>(color: bool, style: Style, text: impl Into<Cow<'a, str>>) -> String {
+    let text = text.into();
+    if color {
+        style.paint(text).to_string()
+    } else {
+        text.into_owned()
+    }
+}
+
+fn diff_paths(left: &Path, right: &Path, option: &DiffOption) -> io::Result<Outcome> {
+    if let Some(output) = &option.interactive {
+        return review_files(left, right, output, option);
+    }
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+    if let Some(format) = option.stat {
+        let mut stats = Vec::new();
+        let outcome = for_each_pair(left, right, option, |pair| {
+            let stat = file_stat(pair, option)?;
+            let differ = stat.is_some();
+            stats.extend(stat);
+            Ok(differ)
+        })?;
+        match format {
+            StatFormat::Stat => {
+                let mut printer = StatPrinter::new(option.color);
+                if option.tty {
+                    printer = printer.with_width(terminal_width());
+                }
+                printer.write_stat(&mut out, &stats)?
+            }
+            StatFormat::NumStat => write_numstat(&mut out, &stats)?,
+        }
+        out.flush()?;
+        return Ok(outcome);
+    }
+    if option.format == OutputFormat::Json {
+        let mut writer = JsonWriter::new(&mut out)?;
+        let outcome = for_each_pair(left, right, option, |pair| {
+            write_json_file(&mut writer, pair, option)
+        })?;
+        writer.finish()?;
+        return Ok(outcome);
+    }
+    if option.format == OutputFormat::Html {
+        let file: Box<dyn Write> = match &option.output {
+            Some(path) => Box::new(io::BufWriter::new(fs::File::create(path)?)),
+            None => Box::new(&mut out),
+        };
+        let title = format!("{} and {}", left.display(), right.display());
+        let view = if option.side_by_side {
+            HtmlView::SideBySide
+        } else {
+            HtmlView::Unified
+        };
+        let mut writer = HtmlWriter::new(file, &title)
+            .with_view(view)
+            .with_index(left.is_dir() && right.is_dir());
+        let outcome = for_each_pair(left, right, option, |pair| {
+            write_html_file(&mut writer, pair, option)
+        })?;
+        writer.finish()?;
+        return Ok(outcome);
+    }
+
+    let outcome = match (left.is_dir(), right.is_dir()) {
+        (true, true) => write_dir_diff(&mut out, left, right, option)?,
+        // Compare with the file of the same name in the directory, like GNU diff does
+        (true, false) => Outcome {
+            differ: write_file_diff(&mut out, &in_dir(left, right)?, right, option)?,
+            trouble: false,
+        },
+        (false, true) => Outcome {
+            differ: write_file_diff(&mut out, left, &in_dir(right, left)?, option)?,
+            trouble: false,
+        },
+        (false, false) => Outcome {
+            differ: write_file_diff(&mut out, left, right, option)?,
+            trouble: false,
+        },
+    };
+    out.flush()?;
+    Ok(outcome)
+}
+
+/// Reviews the hunks of two files in the terminal and writes the merged file
+fn review_files(
+    left: &Path,
+    right: &Path,
+    output: &Path,
+    option: &DiffOption,
+) -> io::Result<Outcome> {
+    if !option.tty || !io::stdin().is_terminal() {
+        return Err(io::Error::other("--interactive needs a terminal"));
+    }
+    let (left, right) = match (left.is_dir(), right.is_dir()) {
+        (true, true) => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "--interactive compares files, not directories",
+            ))
+        }
+        (true, false) => (in_dir(left, right)?, right.to_path_buf()),
+        (false, true) => (left.to_path_buf(), in_dir(right, left)?),
+        (false, false) => (left.to_path_buf(), right.to_path_buf()),
+    };
+    let (left, right) = (load_input(&left)?, load_input(&right)?);
+    check_memory(&[&left, &right], option)?;
+    let (old_text, new_text) = (left.text(), right.text());
+    let findings = Findings::new(
+        (left.encoding(), &old_text),
+        (right.encoding(), &new_text),
+        option.normalizer.strips_trailing_cr(),
+    );
+    let old_lines = split_lines(&old_text);
+    let new_lines = split_lines(&new_text);
+    let (_, hunks) = line_hunks(&old_lines, &new_lines, &findings, option);
+    if hunks.is_empty() {
+        warn!(
+            "{} and {} do not differ, nothing to review",
+            left.path().display(),
+            right.path().display()
+        );
+        return Ok(Outcome::default());
+    }
+
+    let (old_name, new_name) = (
+        left.path().to_string_lossy(),
+        right.path().to_string_lossy(),
+    );
+    let review = Review::new(&old_name, &new_name, &old_lines, &new_lines, &hunks);
+    let decisions = match review.run()? {
+        Some(decisions) => decisions,
+        None => {
+            warn!("Quit without writing {}", output.display());
+            return Ok(Outcome {
+                differ: true,
+                trouble: false,
+            });
+        }
+    };
+    let merged = interactive::merge(&old_lines, &new_lines, &hunks, &decisions);
+    fs::write(output, merged)
+        .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", output.display(), e)))?;
+    Ok(Outcome {
+        differ: true,
+        trouble: false,
+    })
+}
+
+/// Returns the path of a file with the same name as `file` in `dir`
+fn in_dir(dir: &Path, file: &Path) -> io::Result<PathBuf> {
+    match file.file_name() {
+        Some(name) => Ok(dir.join(name)),
+        None => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{}: Not a file name", file.display()),
+        )),
+    }
+}
+
+/// Compares two directory trees, only the differing entries are listed in summary mode,
+/// otherwise every modified file is expanded into a content diff. Quiet mode stops at
+/// the first difference.
+fn write_dir_diff<W: Write>(
+    out: &mut W,
+    left: &Path,
+    right: &Path,
+    option: &DiffOption,
+) -> io::Result<Outcome> {
+    let mut outcome = Outcome::default();
+    let walk = || Walk::new().with_filter(option.filter.clone());
+    let changes = dir::compare_dirs(left, right, walk);
+    // Renames are found among all changes, otherwise the changes are listed as they are
+    // found and quiet mode stops at the first one
+    let (changes, renames): (Box<dyn Iterator<Item = Change>>, _) = match option.renames {
+        Some(threshold) if !option.quiet => {
+            let changes: Vec<Change> = changes.collect();
+            let renames =
+                rename::find_renames(left, right, &changes, walk, threshold, option.copies);
+            (Box::new(changes.into_iter()), renames)
+        }
+        _ => (Box::new(changes), vec![]),
+    };
+    let renamed_old: HashSet<&Path> = renames
+        .iter()
+        .filter(|r| r.kind == RenameKind::Renamed)
+        .map(|r| r.old.as_path())
+        .collect();
+    let renamed_new: HashSet<&Path> = renames.iter().map(|r| r.new.as_path()).collect();
+    // One-sided entries are not listed if all of their files are renamed
+    let all_renamed = |root: &Path, path: &Path, renamed: &HashSet<&Path>| {
+        if renamed.is_empty() {
+            return false;
+        }
+        let files = dir::files_in(&root.join(path), walk());
+        !files.is_empty()
+            && files
+                .iter()
+                .all(|file| file.strip_prefix(root).is_ok_and(|f| renamed.contains(f)))
+    };
+    for change in changes {
+        // Every listed entry differs, only modified files may differ in ignored ways only
+        let ignorable = matches!(change, Change::Modified(_)) && !option.is_exact();
+        if option.quiet && !ignorable {
+            outcome.differ = true;
+            break;
+        }
+        let l = left.join(change.path());
+        let r = right.join(change.path());
+        let only_in = |root: &Path, path: &Path| {
+            let parent = match path.parent() {
+                Some(p) if !p.as_os_str().is_empty() => root.join(p),
+                _ => root.to_path_buf(),
+            };
+            let name = path.file_name().unwrap_or_default();
+            format!("Only in {}: {}", parent.display(), name.to_string_lossy())
+        };
+        match &change {
+            // Reported with the new path
+            Change::LeftOnly(p) if all_renamed(left, p, &renamed_old) => continue,
+            Change::LeftOnly(p) => writeln!(
+                out,
+                "{}",
+                paint(option.color, Red.normal(), only_in(left, p))
+            )?,
+            Change::RightOnly(p) => {
+                if !all_renamed(right, p, &renamed_new) {
+                    writeln!(
+                        out,
+                        "{}",
+                        paint(option.color, Green.normal(), only_in(right, p))
+                    )?;
+                }
+                // The renames into a new directory are listed after it
+                for rename in renames.iter().filter(|r| r.new.starts_with(p)) {
+                    outcome.trouble |= !write_rename(out, left, right, rename, option)?;
+                }
+            }
+            Change::TypeChanged(_) => {
+                let text = format!(
+                    "File {} is a {} while file {} is a {}",
+                    l.display(),
+                    dir::type_name(&l),
+                    r.display(),
+                    dir::type_name(&r)
+                );
+                writeln!(out, "{}", paint(option.color, Yellow.normal(), text))?
+            }
+            Change::LinkChanged(_) => {
+                let target = |link: &Path| match fs::read_link(link) {
+                    Ok(target) => target.display().to_string(),
+                    Err(e) => e.to_string(),
+                };
+                let text = format!(
+                    "Symbolic links {} -> {} and {} -> {} differ",
+                    l.display(),
+                    target(&l),
+                    r.display(),
+                    target(&r)
+                );
+                writeln!(out, "{}", paint(option.color, Yellow.normal(), text))?
+            }
+            // The contents are known to differ, there is nothing to ignore
+            Change::Modified(_) if option.summary || (option.brief && option.is_exact()) => {
+                write_differ(out, &l, &r, option)?
+            }
+            Change::Modified(_) => match write_file_diff(out, &l, &r, option) {
+                Err(e) if e.kind() == io::ErrorKind::BrokenPipe => return Err(e),
+                Err(e) => {
+                    error!("{}", e);
+                    outcome.trouble = true;
+                    continue;
+                }
+                Ok(false) => continue,
+                Ok(true) => {}
+            },
+        }
+        outcome.differ = true;
+        if option.quiet {
+            break;
+        }
+    }
+    Ok(outcome)
+}
+
+/// Reports a renamed or copied file and prints its content changes. Returns `false` if
+/// the contents could not be compared.
+fn write_rename<W: Write>(
+    out: &mut W,
+    left: &Path,
+    right: &Path,
+    rename: &Rename,
+    option: &DiffOption,
+) -> io::Result<bool> {
+    let (l, r) = (left.join(&rename.old), right.join(&rename.new));
+    let kind = match rename.kind {
+        RenameKind::Renamed => "renamed",
+        RenameKind::Copied => "copied",
+    };
+    let text = format!(
+        "{}: {} -> {} ({}%)",
+        kind,
+        l.display(),
+        r.display(),
+        rename.similarity
+    );
+    writeln!(out, "{}", paint(option.color, Yellow.normal(), text))?;
+    if option.summary || option.brief {
+        return Ok(true);
+    }
+    match write_file_diff(out, &l, &r, option) {
+        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => Err(e),
+        Err(e) => {
+            error!("{}", e);
+            Ok(false)
+        }
+        Ok(_) => Ok(true),
+    }
+}
+
+fn write_differ<W: Write>(
+    out: &mut W,
+    left: &Path,
+    right: &Path,
+    option: &DiffOption,
+) -> io::Result<()> {
+    let text = format!("Files {} and {} differ", left.display(), right.display());
+    writeln!(out, "{}", paint(option.color, Style::new().bold(), text))
+}
+
+/// Compares two files and prints their differences. Returns `true` if they differ.
+fn write_file_diff<W: Write>(
+    out: &mut W,
+    left: &Path,
+    right: &Path,
+    option: &DiffOption,
+) -> io::Result<bool> {
+    if option.brief || option.quiet {
+        return write_brief_diff(out, left, right, option);
+    }
+
+    let left = load_input(left)?;
+    let right = load_input(right)?;
+    let binary = option.mode != DiffMode::Bytes && is_binary(&[&left, &right], option);
+
+    if let Some(delimiter) = option.table {
+        return write_table_diff(out, &left, &right, delimiter, option);
+    }
+    if option.set && !binary {
+        return write_set_diff(out, &left, &right, option);
+    }
+    if option.structural && !binary {
+        if let Some(differ) = write_structural_diff(out, &left, &right, option)? {
+            return Ok(differ);
+        }
+        warn!(
+            "{} and {} are not both JSON, TOML or YAML, compare them as text",
+            left.path().display(),
+            right.path().display()
+        );
+    }
+
+    match option.mode {
+        DiffMode::Bytes => write_byte_diff(out, &left, &right, option),
+        _ if binary && option.binary == BinaryMode::Auto => {
+            write_byte_diff(out, &left, &right, option)
+        }
+        _ if binary => write_binary_summary(out, &left, &right, option),
+        DiffMode::Lines | DiffMode::Chars | DiffMode::Words => {
+            write_line_diff(out, &left, &right, option)
+        }
+    }
+}
+
+/// Only reports whether two files differ. Files which must be equal byte by byte are
+/// compared by size first and then block by block, without loading them.
+fn write_brief_diff<W: Write>(
+    out: &mut W,
+    left: &Path,
+    right: &Path,
+    option: &DiffOption,
+) -> io::Result<bool> {
+    let differ = if option.is_exact() {
+        let with_path = |e: io::Error| {
+            io::Error::new(
+                e.kind(),
+                format!("{} and {}: {}", left.display(), right.display(), e),
+            )
+        };
+        !dir::same_content(left, right).map_err(with_path)?
+    } else {
+        let full = DiffOption {
+            brief: false,
+            quiet: false,
+            ..option.clone()
+        };
+        write_file_diff(&mut io::sink(), left, right, &full)?
+    };
+    if differ && option.brief {
+        write_differ(out, left, right, option)?;
+    }
+    Ok(differ)
+}
+
+/// Compares the lines of two files as sets. Returns `true` if a line is in one file only.
+fn write_set_diff<W: Write>(
+    out: &mut W,
+    left: &Input,
+    right: &Input,
+    option: &DiffOption,
+) -> io::Result<bool> {
+    check_memory(&[left, right], option)?;
+    let (old_text, new_text) = (left.text(), right.text());
+    let old_lines = split_lines(&old_text);
+    let new_lines = split_lines(&new_text);
+    let sets = LineSets::new(&old_lines, &new_lines, &option.normalizer);
+    SetPrinter::new(option.color)
+        .with_hidden(option.hidden_groups.clone())
+        .with_counts(option.count)
+        .write_sets(
+            out,
+            &sets,
+            &left.path().to_string_lossy(),
+            &right.path().to_string_lossy(),
+        )?;
+    Ok(sets.differ(option.count))
+}
+
+/// Compares two tables row by row, the rows are matched by their key columns
+fn write_table_diff<W: Write>(
+    out: &mut W,
+    left: &Input,
+    right: &Input,
+    delimiter: u8,
+    option: &DiffOption,
+) -> io::Result<bool> {
+    let read = |input: &Input| {
+        Table::read(input.bytes(), delimiter).map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}: {}", input.path().display(), e),
+            )
+        })
+    };
+    let (old, new) = (read(left)?, read(right)?);
+    let changes = table::compare_tables(&old, &new, &option.keys)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    if changes.is_empty() {
+        return Ok(false);
+    }
+    UnifiedPrinter::new(option.color).write_header(
+        out,
+        &left.path().to_string_lossy(),
+        &right.path().to_string_lossy(),
+    )?;
+    table::write_changes(out, &changes, option.color)?;
+    Ok(true)
+}
+
+/// Compares two documents as data trees. Returns whether they differ, or `None` if
+/// either of them is not JSON, TOML or YAML.
+fn write_structural_diff<W: Write>(
+    out: &mut W,
+    left: &Input,
+    right: &Input,
+    option: &DiffOption,
+) -> io::Result<Option<bool>> {
+    let (old_text, new_text) = (left.text(), right.text());
+    let old = structural::parse(left.path(), &old_text);
+    let new = structural::parse(right.path(), &new_text);
+    let (old, new) = match (old, new) {
+        (Some(old), Some(new)) => (old, new),
+        _ => return Ok(None),
+    };
+
+    let changes = structural::compare(&old, &new);
+    if !changes.is_empty() {
+        UnifiedPrinter::new(option.color).write_header(
+            out,
+            &left.path().to_string_lossy(),
+            &right.path().to_string_lossy(),
+        )?;
+        structural::write_changes(out, &changes, option.color)?;
+    }
+    Ok(Some(!changes.is_empty()))
+}
+
+fn load_input(path: &Path) -> io::Result<Input> {
+    Input::from_path(path)
+        .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path.display(), e)))
+}
+
+/// Two files to compare, a missing side is an added or a deleted file
+struct FilePair {
+    left: Option<PathBuf>,
+    right: Option<PathBuf>,
+    name: String,
+}
+
+impl FilePair {
+    fn new(left: Option<PathBuf>, right: Option<PathBuf>, name: String) -> Self {
+        Self { left, right, name }
+    }
+}
+
+/// Lists the pairs of files to compare in path order, directories are compared entry by
+/// entry and the files of one-sided directories are listed one by one. Entries which
+/// changed their type are not listed.
+fn file_pairs(left: &Path, right: &Path, option: &DiffOption) -> io::Result<Vec<FilePair>> {
+    let pairs = match (left.is_dir(), right.is_dir()) {
+        (true, true) => {
+            let walk = || Walk::new().with_filter(option.filter.clone());
+            let relative = |root: &Path, file: &Path| {
+                file.strip_prefix(root)
+                    .unwrap_or(file)
+                    .display()
+                    .to_string()
+            };
+            let mut pairs = Vec::new();
+            let changes: Vec<Change> = dir::compare_dirs(left, right, walk).collect();
+            let renames = match option.renames {
+                Some(threshold) => {
+                    rename::find_renames(left, right, &changes, walk, threshold, option.copies)
+                }
+                None => vec![],
+            };
+            for change in changes {
+                let name = change.path().display().to_string();
+                let (l, r) = (left.join(change.path()), right.join(change.path()));
+                match change {
+                    Change::Modified(_) => pairs.push(FilePair::new(Some(l), Some(r), name)),
+                    Change::LeftOnly(_) => {
+                        pairs.extend(dir::files_in(&l, walk()).into_iter().map(|file| {
+                            FilePair::new(Some(file.clone()), None, relative(left, &file))
+                        }))
+                    }
+                    Change::RightOnly(_) => {
+                        pairs.extend(dir::files_in(&r, walk()).into_iter().map(|file| {
+                            FilePair::new(None, Some(file.clone()), relative(right, &file))
+                        }))
+                    }
+                    Change::TypeChanged(_) | Change::LinkChanged(_) => {}
+                }
+            }
+            // Renamed files are compared with their old versions
+            let is_renamed = |pair: &FilePair| {
+                renames.iter().any(|r| {
+                    (r.kind == RenameKind::Renamed
+                        && pair.left.as_deref() == Some(&left.join(&r.old))
+                        && pair.right.is_none())
+                        || (pair.right.as_deref() == Some(&right.join(&r.new))
+                            && pair.left.is_none())
+                })
+            };
+            pairs.retain(|pair| !is_renamed(pair));
+            pairs.extend(renames.iter().map(|r| {
+                let name = format!("{} => {}", r.old.display(), r.new.display());
+                FilePair::new(Some(left.join(&r.old)), Some(right.join(&r.new)), name)
+            }));
+            // Renamed files are listed at their new paths, deleted files at their old ones
+            let key = |pair: &FilePair| match (&pair.left, &pair.right) {
+                (_, Some(file)) => file.strip_prefix(right).unwrap_or(file).to_path_buf(),
+                (Some(file), None) => file.strip_prefix(left).unwrap_or(file).to_path_buf(),
+                (None, None) => PathBuf::new(),
+            };
+            pairs.sort_by_cached_key(key);
+            pairs
+        }
+        (true, false) => {
+            let l = in_dir(left, right)?;
+            let name = stat_name(&l, right);
+            vec![FilePair::new(Some(l), Some(right.to_path_buf()), name)]
+        }
+        (false, true) => {
+            let r = in_dir(right, left)?;
+            let name = stat_name(left, &r);
+            vec![FilePair::new(Some(left.to_path_buf()), Some(r), name)]
+        }
+        (false, false) => vec![FilePair::new(
+            Some(left.to_path_buf()),
+            Some(right.to_path_buf()),
+            stat_name(left, right),
+        )],
+    };
+    Ok(pairs)
+}
+
+/// Passes each pair of files to `write`, which returns `true` if the files differ. A pair
+/// which can not be compared is reported and skipped.
+fn for_each_pair(
+    left: &Path,
+    right: &Path,
+    option: &DiffOption,
+    mut write: impl FnMut(&FilePair) -> io::Result<bool>,
+) -> io::Result<Outcome> {
+    let mut outcome = Outcome::default();
+    for pair in file_pairs(left, right, option)? {
+        match write(&pair) {
+            Err(e) if e.kind() == io::ErrorKind::BrokenPipe => return Err(e),
+            Err(e) => {
+                error!("{}", e);
+                outcome.trouble = true;
+            }
+            Ok(differ) => outcome.differ |= differ,
+        }
+    }
+    Ok(outcome)
+}
+
+/// Returns the name of a compared pair of files, like git shows renames
+fn stat_name(left: &Path, right: &Path) -> String {
+    if left == right {
+        left.display().to_string()
+    } else {
+        format!("{} => {}", left.display(), right.display())
+    }
+}
+
+/// The loaded files of a pair, a missing file is empty
+struct LoadedPair {
+    left: Option<Input>,
+    right: Option<Input>,
+    binary: bool,
+}
+
+/// How the files of a pair differ
+enum PairDiff<'a> {
+    /// The files are binary or compared byte by byte, only their sizes are shown
+    Binary {
+        old_size: usize,
+        new_size: usize,
+    },
+    Lines(LineDiff<'a>),
+}
+
+/// The line differences of two texts
+struct LineDiff<'a> {
+    old_lines: Vec<&'a str>,
+    new_lines: Vec<&'a str>,
+    ops: Vec<DiffOp>,
+    hunks: Vec<Hunk>,
+    findings: Findings,
+}
+
+impl LoadedPair {
+    /// Loads both files. The output formats which list whole files can not show a byte
+    /// diff, so `--bytes` compares the files as binary.
+    fn load(pair: &FilePair, option: &DiffOption) -> io::Result<Self> {
+        let left = pair.left.as_deref().map(load_input).transpose()?;
+        let right = pair.right.as_deref().map(load_input).transpose()?;
+        let inputs: Vec<&Input> = left.iter().chain(right.iter()).collect();
+        let binary = option.mode == DiffMode::Bytes || is_binary(&inputs, option);
+        Ok(Self {
+            left,
+            right,
+            binary,
+        })
+    }
+
+    /// Compares the files and passes their differences to `f`. Returns `None` without
+    /// calling `f` if the files do not differ.
+    fn compare<T>(
+        &self,
+        option: &DiffOption,
+        f: impl FnOnce(PairDiff<'_>) -> io::Result<T>,
+    ) -> io::Result<Option<T>> {
+        let (old_bytes, new_bytes) = (self.old_bytes(), self.new_bytes());
+        if self.binary {
+            if old_bytes == new_bytes {
+                return Ok(None);
+            }
+            let (old_size, new_size) = (old_bytes.len(), new_bytes.len());
+            return f(PairDiff::Binary { old_size, new_size }).map(Some);
+        }
+
+        let inputs: Vec<&Input> = self.left.iter().chain(self.right.iter()).collect();
+        check_memory(&inputs, option)?;
+        let (old_text, new_text) = (self.old_text(), self.new_text());
+        let findings = self.findings(&old_text, &new_text, option);
+        let old_lines = split_lines(&old_text);
+        let new_lines = split_lines(&new_text);
+        let (ops, hunks) = line_hunks(&old_lines, &new_lines, &findings, option);
+        // Files which differ only in their encoding or line endings are listed without lines
+        if hunks.is_empty() && findings.is_empty() {
+            return Ok(None);
+        }
+        f(PairDiff::Lines(LineDiff {
+            old_lines,
+            new_lines,
+            ops,
+            hunks,
+            findings,
+        }))
+        .map(Some)
+    }
+
+    /// Returns the encoding and line ending differences, an added or deleted file has none
+    fn findings(&self, old_text: &str, new_text: &str, option: &DiffOption) -> Findings {
+        match (&self.left, &self.right) {
+            (Some(left), Some(right)) => Findings::new(
+                (left.encoding(), old_text),
+                (right.encoding(), new_text),
+                option.normalizer.strips_trailing_cr(),
+            ),
+            _ => Findings::default(),
+        }
+    }
+
+    fn old_bytes(&self) -> &[u8] {
+        self.left.as_ref().map_or(&[], Input::bytes)
+    }
+
+    fn new_bytes(&self) -> &[u8] {
+        self.right.as_ref().map_or(&[], Input::bytes)
+    }
+
+    fn old_text(&self) -> Cow<'_, str> {
+        self.left.as_ref().map_or(Cow::Borrowed(""), Input::text)
+    }
+
+    fn new_text(&self) -> Cow<'_, str> {
+        self.right.as_ref().map_or(Cow::Borrowed(""), Input::text)
+    }
+}
+
+/// Returns `true` if one of the inputs is binary and should not be compared as text
+fn is_binary(inputs: &[&Input], option: &DiffOption) -> bool {
+    option.binary != BinaryMode::Text && inputs.iter().any(|input| input.is_binary())
+}
+
+/// Counts the changed lines of two files. Returns `None` if the files do not differ.
+fn file_stat(pair: &FilePair, option: &DiffOption) -> io::Result<Option<FileStat>> {
+    let name = pair.name.clone();
+    LoadedPair::load(pair, option)?.compare(option, |diff| {
+        Ok(match diff {
+            PairDiff::Binary { old_size, new_size } => FileStat::binary(name, old_size, new_size),
+            PairDiff::Lines(lines) => FileStat::from_hunks(name, &lines.hunks),
+        })
+    })
+}
+
+/// Writes the JSON entry of two files if they differ. Returns `true` if they differ.
+fn write_json_file<W: Write>(
+    writer: &mut JsonWriter<W>,
+    pair: &FilePair,
+    option: &DiffOption,
+) -> io::Result<bool> {
+    let path = |path: &Option<PathBuf>| path.as_ref().map(|p| p.display().to_string());
+    let (old_path, new_path) = (path(&pair.left), path(&pair.right));
+    let written = LoadedPair::load(pair, option)?.compare(option, |diff| match diff {
+        PairDiff::Binary { .. } => writer.write_file(&FileDiff::binary(old_path, new_path)),
+        PairDiff::Lines(lines) => {
+            let moved = find_moved_blocks(&lines.ops, &lines.old_lines, &lines.new_lines);
+            let file = FileDiff::new(
+                old_path,
+                new_path,
+                &lines.hunks,
+                &lines.old_lines,
+                &lines.new_lines,
+                &moved,
+            )
+            .with_findings(&lines.findings);
+            writer.write_file(&file)
+        }
+    })?;
+    Ok(written.is_some())
+}
+
+/// Adds the section of two files to the report if they differ. Returns `true` if they
+/// differ.
+fn write_html_file<W: Write>(
+    writer: &mut HtmlWriter<W>,
+    pair: &FilePair,
+    option: &DiffOption,
+) -> io::Result<bool> {
+    let written = LoadedPair::load(pair, option)?.compare(option, |diff| {
+        match diff {
+            PairDiff::Binary { old_size, new_size } => {
+                writer.write_binary(&pair.name, old_size, new_size)
+            }
+            PairDiff::Lines(lines) => writer.write_file(
+                &pair.name,
+                &lines.hunks,
+                &lines.old_lines,
+                &lines.new_lines,
+                &lines.findings,
+            ),
+        }
+        Ok(())
+    })?;
+    Ok(written.is_some())
+}
+
+/// Fails if a line diff of the inputs would need more memory than allowed
+fn check_memory(inputs: &[&Input], option: &DiffOption) -> io::Result<()> {
+    let limit = match option.max_memory {
+        Some(limit) => limit,
+        None => return Ok(()),
+    };
+    let needed: u64 = inputs.iter().map(|i| i.line_diff_memory()).sum();
+    if needed <= limit {
+        return Ok(());
+    }
+    let names: Vec<String> = inputs
+        .iter()
+        .map(|i| i.path().display().to_string())
+        .collect();
+    Err(io::Error::new(
+        io::ErrorKind::OutOfMemory,
+        format!(
+            "Comparing {} needs about {} of memory, more than the limit of {}",
+            names.join(" and "),
+            format_size(needed),
+            format_size(limit)
+        ),
+    ))
+}
+
+/// Compares the lines and returns the edit script and the hunks which are not ignored.
+/// Carriage returns are ignored if the line endings of the files differ.
+fn line_hunks(
+    old_lines: &[&str],
+    new_lines: &[&str],
+    findings: &Findings,
+    option: &DiffOption,
+) -> (Vec<DiffOp>, Vec<Hunk>) {
+    let normalizer = if findings.line_endings_differ() {
+        Cow::Owned(option.normalizer.clone().strip_trailing_cr(true))
+    } else {
+        Cow::Borrowed(&option.normalizer)
+    };
+    let (old_ids, new_ids) = {
+        let old_keys: Vec<_> = old_lines.iter().map(|l| normalizer.key(l)).collect();
+        let new_keys: Vec<_> = new_lines.iter().map(|l| normalizer.key(l)).collect();
+        line_ids(&old_keys, &new_keys)
+    };
+    let ops = option.algorithm.diff(&old_ids, &new_ids);
+    let mut hunks = group_hunks(&ops, option.context);
+    hunks.retain(|hunk| !normalizer.is_ignorable_hunk(hunk, old_lines, new_lines));
+    (ops, hunks)
+}
+
+/// Reports that two binary inputs differ, with their sizes and the first differing offset
+fn write_binary_summary<W: Write>(
+    out: &mut W,
+    left: &Input,
+    right: &Input,
+    option: &DiffOption,
+) -> io::Result<bool> {
+    let (old, new) = (left.bytes(), right.bytes());
+    let offset = common_prefix_len(old, new);
+    if offset == old.len() && offset == new.len() {
+        return Ok(false);
+    }
+
+    let text = format!(
+        "Binary files {} and {} differ",
+        left.path().display(),
+        right.path().display()
+    );
+    writeln!(out, "{}", paint(option.color, Style::new().bold(), text))?;
+    writeln!(
+        out,
+        "  sizes {} and {} bytes, first difference at offset {} (0x{:08x})",
+        old.len(),
+        new.len(),
+        offset,
+        offset
+    )?;
+    Ok(true)
+}
+
+/// Compares two inputs line by line and prints the unified diff
+fn write_line_diff<W: Write>(
+    out: &mut W,
+    left: &Input,
+    right: &Input,
+    option: &DiffOption,
+) -> io::Result<bool> {
+    check_memory(&[left, right], option)?;
+    let (old_text, new_text) = (left.text(), right.text());
+    let findings = Findings::new(
+        (left.encoding(), &old_text),
+        (right.encoding(), &new_text),
+        option.normalizer.strips_trailing_cr(),
+    );
+    let old_lines = split_lines(&old_text);
+    let new_lines = split_lines(&new_text);
+    let (ops, hunks) = line_hunks(&old_lines, &new_lines, &findings, option);
+    if hunks.is_empty() && findings.is_empty() {
+        return Ok(false);
+    }
+
+    let (old_name, new_name) = (
+        left.path().to_string_lossy(),
+        right.path().to_string_lossy(),
+    );
+    let functions = if option.function_context {
+        FunctionMatcher::for_path(left.path()).or_else(|| FunctionMatcher::for_path(right.path()))
+    } else {
+        None
+    };
+    if option.side_by_side && option.tty {
+        let mut printer = SideBySidePrinter::new(option.color)
+            .with_width(terminal_width())
+            .with_wrap(option.wrap);
+        if let Some(functions) = functions {
+            printer = printer.with_functions(functions);
+        }
+        printer.write_header(out, &old_name, &new_name)?;
+        findings.write(out, option.color)?;
+        for hunk in &hunks {
+            printer.write_hunk(out, hunk, &old_lines, &new_lines)?;
+        }
+    } else if option.mode == DiffMode::Words {
+        UnifiedPrinter::new(option.color).write_header(out, &old_name, &new_name)?;
+        findings.write(out, option.color)?;
+        let mut printer = WordPrinter::new(option.color).with_algorithm(option.algorithm);
+        if let Some(functions) = functions {
+            printer = printer.with_functions(functions);
+        }
+        for hunk in &hunks {
+            printer.write_hunk(out, hunk, &old_lines, &new_lines)?;
+        }
+    } else {
+        let mut printer = UnifiedPrinter::new(option.color)
+            .with_inline_highlight(option.mode == DiffMode::Chars)
+            .with_encodings(left.encoding(), right.encoding());
+        if option.color_moved && option.color {
+            let blocks = find_moved_blocks(&ops, &old_lines, &new_lines);
+            printer =
+                printer.with_moved(MovedLines::new(&blocks, old_lines.len(), new_lines.len()));
+        }
+        if let Some(functions) = functions {
+            printer = printer.with_functions(functions);
+        }
+        if option.syntax && option.color {
+            // Hunks are in order, so the last one shows the last lines
+            let (old_end, new_end) = hunks
+                .last()
+                .map_or((0, 0), |h| (h.old_range().end, h.new_range().end));
+            let syntax = SyntaxColors::new(right.path(), &old_lines, &new_lines, old_end, new_end)
+                .or_else(|| {
+                    SyntaxColors::new(left.path(), &old_lines, &new_lines, old_end, new_end)
+                });
+            if let Some(syntax) = syntax {
+                printer = printer.with_syntax(syntax);
+            }
+        }
+        printer.write_header(out, &old_name, &new_name)?;
+        findings.write(out, option.color)?;
+        for hunk in &hunks {
+            printer.write_hunk(out, hunk, &old_lines, &new_lines)?;
+        }
+    }
+    Ok(true)
+}
+
+/// Compares two inputs byte by byte and prints the differing rows as hex dump
+fn write_byte_diff<W: Write>(
+    out: &mut W,
+    left: &Input,
+    right: &Input,
+    option: &DiffOption,
+) -> io::Result<bool> {
+    let ops = bytes::diff_bytes(left.bytes(), right.bytes(), option.resync);
+    if ops.iter().all(|op| op.is_equal()) {
+        return Ok(false);
+    }
+
+    UnifiedPrinter::new(option.color).write_header(
+        out,
+        &left.path().to_string_lossy(),
+        &right.path().to_string_lossy(),
+    )?;
+    HexPrinter::new(option.color)
+        .with_context(option.context)
+        .write_diff(out, left.bytes(), right.bytes(), &ops)?;
+    Ok(true)
 }
@@ -0,0 +1,58 @@
+use super::ops::{common_prefix_len, common_suffix_len, Capture, DiffOp};
+use super::{histogram, myers, patience};
+use std::hash::Hash;
+use std::str::FromStr;
+
+/// The algorithm which computes the edit script
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Algorithm {
+    /// Myers with a cost limit, fast and usually minimal
+    #[default]
+    Myers,
+    /// Myers without the cost limit, always finds the shortest edit script
+    Minimal,
+    /// Anchors on lines which are unique on both sides
+    Patience,
+    /// Anchors on the least frequent lines, like git does
+    Histogram,
+}
+
+impl Algorithm {
+    pub const NAMES: &'static [&'static str] = &["myers", "minimal", "patience", "histogram"];
+
+    /// Returns the edit script which turns `old` into `new`.
+    ///
+    /// The common prefix and suffix are trimmed first, so the work space of the algorithm
+    /// only depends on the size of the changed region.
+    pub fn diff<T: Hash + Eq>(self, old: &[T], new: &[T]) -> Vec<DiffOp> {
+        let mut capture = Capture::new();
+        let prefix = common_prefix_len(old, new);
+        capture.equal(0, 0, prefix);
+        let (old, new) = (&old[prefix..], &new[prefix..]);
+        let suffix = common_suffix_len(old, new);
+        let (old, new) = (&old[..old.len() - suffix], &new[..new.len() - suffix]);
+
+        match self {
+            Algorithm::Myers => myers::diff_into(old, prefix, new, prefix, false, &mut capture),
+            Algorithm::Minimal => myers::diff_into(old, prefix, new, prefix, true, &mut capture),
+            Algorithm::Patience => patience::diff_into(old, prefix, new, prefix, &mut capture),
+            Algorithm::Histogram => histogram::diff_into(old, prefix, new, prefix, &mut capture),
+        }
+        capture.equal(prefix + old.len(), prefix + new.len(), suffix);
+        capture.into_ops()
+    }
+}
+
+impl FromStr for Algorithm {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "myers" => Ok(Algorithm::Myers),
+            "minimal" => Ok(Algorithm::Minimal),
+            "patience" => Ok(Algorithm::Patience),
+            "histogram" => Ok(Algorithm::Histogram),
+            _ => Err(format!("Unknown diff algorithm: {}", s)),
+        }
+    }
+}
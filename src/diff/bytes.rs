@@ -0,0 +1,383 @@
+//! Binary comparison, printed as aligned hex and ASCII rows like `hexdump -C`.
+
+use super::ops::{common_prefix_len, Capture, DiffOp};
+use super::paint;
+use ansi_term::{ANSIString, ANSIStrings, Color::*, Style};
+use std::cmp::Ordering;
+use std::io::{self, Write};
+use std::ops::Range;
+
+/// The number of bytes of a dump row
+const ROW_LEN: usize = 16;
+
+/// How far a resynchronisation looks ahead on each side after a mismatch
+const RESYNC_WINDOW: usize = 128;
+
+/// How many bytes must match again before both sides are considered in sync
+const RESYNC_LEN: usize = 16;
+
+/// The end of a chain of offsets in `find_resync`
+const NONE: usize = usize::MAX;
+
+/// Compares two byte sequences.
+///
+/// Without `resync` the bytes are compared by position like `cmp -l`. With `resync`
+/// a mismatch is followed by a search for the nearest offsets where both sides match
+/// again, so an inserted or deleted byte only shows up as a single change.
+pub fn diff_bytes(old: &[u8], new: &[u8], resync: bool) -> Vec<DiffOp> {
+    let mut capture = Capture::new();
+    let (mut i, mut j) = (0, 0);
+
+    while i < old.len() && j < new.len() {
+        let same = common_prefix_len(&old[i..], &new[j..]);
+        capture.equal(i, j, same);
+        i += same;
+        j += same;
+        if i == old.len() || j == new.len() {
+            break;
+        }
+
+        let (di, dj) = if resync {
+            find_resync(&old[i..], &new[j..])
+        } else {
+            (1, 1)
+        };
+        capture.delete(i, di, j);
+        capture.insert(i + di, j, dj);
+        i += di;
+        j += dj;
+    }
+
+    // Files of different lengths
+    capture.delete(i, old.len() - i, j);
+    capture.insert(old.len(), j, new.len() - j);
+    capture.into_ops()
+}
+
+/// Returns how many bytes to skip on each side to get both sides in sync again,
+/// the shortest skip wins and a substitution is preferred over an insertion.
+fn find_resync(old: &[u8], new: &[u8]) -> (usize, usize) {
+    let max_old = RESYNC_WINDOW.min(old.len());
+    let max_new = RESYNC_WINDOW.min(new.len());
+
+    // Chains the offsets of `new` by their byte, so only the offsets which start with
+    // the same byte as `old[di]` are tried
+    let mut first = [NONE; 256];
+    let mut next = [NONE; RESYNC_WINDOW + 1];
+    for dj in (0..=max_new.min(new.len() - 1)).rev() {
+        next[dj] = first[new[dj] as usize];
+        first[new[dj] as usize] = dj;
+    }
+
+    // Ordered by the total skip, then by the distance to the balanced split, then by
+    // the skip of `old`. Skipping both windows is the fallback.
+    let key = |di: usize, dj: usize| (di + dj, di.abs_diff(dj), di);
+    let mut best = (max_old, max_new);
+    for di in 0..=max_old.min(old.len() - 1) {
+        if di > best.0 + best.1 {
+            break;
+        }
+        let mut dj = first[old[di] as usize];
+        while dj != NONE && di + dj <= best.0 + best.1 {
+            if key(di, dj) < key(best.0, best.1) && in_sync(&old[di..], &new[dj..]) {
+                best = (di, dj);
+            }
+            dj = next[dj];
+        }
+    }
+    best
+}
+
+#[inline]
+fn in_sync(old: &[u8], new: &[u8]) -> bool {
+    let len = RESYNC_LEN.min(old.len()).min(new.len());
+    old[..len] == new[..len]
+}
+
+/// A group of changes which is printed under one header
+struct Region {
+    old_bytes: Range<usize>,
+    new_bytes: Range<usize>,
+    blocks: Vec<Block>,
+}
+
+/// The rows of the dump on each side which contain changes, changes in the same or in
+/// adjacent rows share a block. The rows between the blocks of a region are unchanged.
+struct Block {
+    old_rows: Range<usize>,
+    new_rows: Range<usize>,
+    /// The old row at which the context after the block starts. An insertion has no old
+    /// rows, the context starts past the offsets of the inserted rows so that the old row
+    /// at the insertion is not shown again after them.
+    context_start: usize,
+}
+
+fn row_range(bytes: &Range<usize>) -> Range<usize> {
+    if bytes.is_empty() {
+        bytes.start / ROW_LEN..bytes.start / ROW_LEN
+    } else {
+        bytes.start / ROW_LEN..bytes.end.div_ceil(ROW_LEN)
+    }
+}
+
+/// Prints the rows of a binary comparison which contain differences
+pub struct HexPrinter {
+    color: bool,
+    context: usize,
+}
+
+impl HexPrinter {
+    pub fn new(color: bool) -> Self {
+        Self { color, context: 0 }
+    }
+
+    /// Sets the number of unchanged rows printed around a change
+    pub fn with_context(mut self, rows: usize) -> Self {
+        self.context = rows;
+        self
+    }
+
+    pub fn write_diff<W: Write>(
+        &self,
+        out: &mut W,
+        old: &[u8],
+        new: &[u8],
+        ops: &[DiffOp],
+    ) -> io::Result<()> {
+        let old_changes: Vec<_> = ops
+            .iter()
+            .filter(|op| !op.is_equal())
+            .map(|op| op.old_range())
+            .collect();
+        let new_changes: Vec<_> = ops
+            .iter()
+            .filter(|op| !op.is_equal())
+            .map(|op| op.new_range())
+            .collect();
+        let old_row_count = old.len().div_ceil(ROW_LEN);
+
+        let regions = self.regions(ops);
+        for region in &regions {
+            writeln!(
+                out,
+                "{}",
+                paint(
+                    self.color,
+                    Cyan.normal(),
+                    format!(
+                        "@@ -0x{:08x},{} +0x{:08x},{} @@",
+                        region.old_bytes.start,
+                        region.old_bytes.len(),
+                        region.new_bytes.start,
+                        region.new_bytes.len()
+                    )
+                )
+            )?;
+
+            let start = region.blocks[0].old_rows.start;
+            let mut context = start.saturating_sub(self.context)..start;
+            for block in &region.blocks {
+                for row in context.start..block.old_rows.start {
+                    self.write_row(out, ' ', Style::new(), old, row, &[])?;
+                }
+                for row in block.old_rows.clone() {
+                    self.write_row(out, '-', Red.normal(), old, row, &old_changes)?;
+                }
+                for row in block.new_rows.clone() {
+                    self.write_row(out, '+', Green.normal(), new, row, &new_changes)?;
+                }
+                let context_start = block.context_start.min(old_row_count);
+                context = context_start..context_start;
+            }
+            let trailing = (context.start + self.context).min(old_row_count);
+            for row in context.start..trailing {
+                self.write_row(out, ' ', Style::new(), old, row, &[])?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Groups the changes into regions, changes closer than `2 * context` rows
+    /// share the same region.
+    fn regions(&self, ops: &[DiffOp]) -> Vec<Region> {
+        let mut regions: Vec<Region> = Vec::new();
+        for op in ops.iter().filter(|op| !op.is_equal()) {
+            let (old_bytes, new_bytes) = (op.old_range(), op.new_range());
+            let old_rows = row_range(&old_bytes);
+            let context_start = if old_bytes.is_empty() {
+                (old_bytes.start + new_bytes.len()).div_ceil(ROW_LEN)
+            } else {
+                old_rows.end
+            };
+            let block = Block {
+                old_rows,
+                new_rows: row_range(&new_bytes),
+                context_start,
+            };
+            if let Some(region) = regions.last_mut() {
+                let last = region.blocks.last_mut().expect("a region has a block");
+                // The unchanged rows between the last block and this one
+                let gap = block
+                    .old_rows
+                    .start
+                    .saturating_sub(last.old_rows.end)
+                    .min(block.new_rows.start.saturating_sub(last.new_rows.end));
+                if gap <= 2 * self.context {
+                    if gap == 0 {
+                        last.old_rows.end = last.old_rows.end.max(block.old_rows.end);
+                        last.new_rows.end = last.new_rows.end.max(block.new_rows.end);
+                        last.context_start = last.context_start.max(block.context_start);
+                    } else {
+                        region.blocks.push(block);
+                    }
+                    region.old_bytes.end = old_bytes.end;
+                    region.new_bytes.end = new_bytes.end;
+                    continue;
+                }
+            }
+            regions.push(Region {
+                old_bytes,
+                new_bytes,
+                blocks: vec![block],
+            });
+        }
+        regions
+    }
+
+    fn write_row<W: Write>(
+        &self,
+        out: &mut W,
+        marker: char,
+        style: Style,
+        data: &[u8],
+        row: usize,
+        changes: &[Range<usize>],
+    ) -> io::Result<()> {
+        let offset = row * ROW_LEN;
+        let bytes = &data[offset.min(data.len())..(offset + ROW_LEN).min(data.len())];
+        let highlight = |i: usize| self.color && is_changed(changes, offset + i);
+        let emphasis = style.reverse();
+
+        let mut painted: Vec<ANSIString> = Vec::with_capacity(2 * ROW_LEN + 4);
+        painted.push(style.paint(format!("{} {:08x}  ", marker, offset)));
+        for i in 0..ROW_LEN {
+            let cell = match bytes.get(i) {
+                Some(b) => format!("{:02x}", b),
+                None => "  ".to_string(),
+            };
+            painted.push(if highlight(i) {
+                emphasis.paint(cell)
+            } else {
+                style.paint(cell)
+            });
+            painted.push(style.paint(if i == ROW_LEN / 2 - 1 { "  " } else { " " }));
+        }
+        painted.push(style.paint(" |"));
+        for (i, b) in bytes.iter().enumerate() {
+            let c = if b.is_ascii_graphic() || *b == b' ' {
+                *b as char
+            } else {
+                '.'
+            };
+            painted.push(if highlight(i) {
+                emphasis.paint(c.to_string())
+            } else {
+                style.paint(c.to_string())
+            });
+        }
+        painted.push(style.paint("|"));
+
+        if self.color {
+            writeln!(out, "{}", ANSIStrings(&painted))
+        } else {
+            let plain: String = painted.iter().map(|s| &**s).collect();
+            writeln!(out, "{}", plain)
+        }
+    }
+}
+
+/// Returns `true` if `pos` lies in one of the sorted `changes`
+fn is_changed(changes: &[Range<usize>], pos: usize) -> bool {
+    changes
+        .binary_search_by(|r| {
+            if r.end <= pos {
+                Ordering::Less
+            } else if r.start > pos {
+                Ordering::Greater
+            } else {
+                Ordering::Equal
+            }
+        })
+        .is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diff::ops::apply;
+
+    fn dump(old: &[u8], new: &[u8], context: usize) -> String {
+        let ops = diff_bytes(old, new, true);
+        let mut out = Vec::new();
+        HexPrinter::new(false)
+            .with_context(context)
+            .write_diff(&mut out, old, new, &ops)
+            .unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    fn changes(ops: &[DiffOp]) -> Vec<(Range<usize>, Range<usize>)> {
+        ops.iter()
+            .filter(|op| !op.is_equal())
+            .map(|op| (op.old_range(), op.new_range()))
+            .collect()
+    }
+
+    #[test]
+    fn an_inserted_byte_is_one_change() {
+        let old = b"the quick brown fox jumps over the lazy dog";
+        let mut new = old.to_vec();
+        new.insert(4, b'!');
+        let ops = diff_bytes(old, &new, true);
+        assert_eq!(changes(&ops), vec![(4..4, 4..5)]);
+        assert_eq!(apply(&ops, old, &new), new);
+
+        // Compared by position every following byte differs
+        let ops = diff_bytes(old, &new, false);
+        assert_eq!(changes(&ops), vec![(4..old.len(), 4..new.len())]);
+        assert_eq!(apply(&ops, old, &new), new);
+    }
+
+    #[test]
+    fn changed_rows_are_printed_with_context() {
+        let old: Vec<u8> = (0..48).collect();
+        let mut new = old.clone();
+        new[20] = 0xff;
+        assert_eq!(
+            dump(&old, &new, 1),
+            "\
+@@ -0x00000014,1 +0x00000014,1 @@
+  00000000  00 01 02 03 04 05 06 07  08 09 0a 0b 0c 0d 0e 0f  |................|
+- 00000010  10 11 12 13 14 15 16 17  18 19 1a 1b 1c 1d 1e 1f  |................|
++ 00000010  10 11 12 13 ff 15 16 17  18 19 1a 1b 1c 1d 1e 1f  |................|
+  00000020  20 21 22 23 24 25 26 27  28 29 2a 2b 2c 2d 2e 2f  | !\"#$%&'()*+,-./|
+"
+        );
+    }
+
+    #[test]
+    fn context_after_an_insertion_starts_past_it() {
+        let old: Vec<u8> = (0..48).collect();
+        let mut new = old.clone();
+        new.splice(16..16, [0xee; 16]);
+        assert_eq!(
+            dump(&old, &new, 1),
+            "\
+@@ -0x00000010,0 +0x00000010,16 @@
+  00000000  00 01 02 03 04 05 06 07  08 09 0a 0b 0c 0d 0e 0f  |................|
++ 00000010  ee ee ee ee ee ee ee ee  ee ee ee ee ee ee ee ee  |................|
+  00000020  20 21 22 23 24 25 26 27  28 29 2a 2b 2c 2d 2e 2f  | !\"#$%&'()*+,-./|
+"
+        );
+    }
+}
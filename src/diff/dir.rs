@@ -0,0 +1,277 @@
+use crate::walk::{DirEntry, Walk};
+use std::cmp::Ordering;
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::iter::Peekable;
+use std::path::{Path, PathBuf};
+
+/// The block size used when comparing file contents
+const BLOCK_SIZE: usize = 64 * 1024;
+
+/// A difference between two directory trees, paths are relative to the compared roots
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Change {
+    LeftOnly(PathBuf),
+    RightOnly(PathBuf),
+    TypeChanged(PathBuf),
+    Modified(PathBuf),
+    /// Symbolic links to different targets
+    LinkChanged(PathBuf),
+}
+
+impl Change {
+    pub fn path(&self) -> &Path {
+        match self {
+            Change::LeftOnly(p)
+            | Change::RightOnly(p)
+            | Change::TypeChanged(p)
+            | Change::Modified(p)
+            | Change::LinkChanged(p) => p,
+        }
+    }
+}
+
+/// Returns a readable name of the entry type, like `regular file` or `directory`
+pub fn type_name(path: &Path) -> &'static str {
+    match path.symlink_metadata() {
+        Ok(meta) if meta.file_type().is_symlink() => "symbolic link",
+        Ok(meta) if meta.is_dir() => "directory",
+        Ok(meta) if meta.is_file() => "regular file",
+        Ok(_) => "special file",
+        Err(_) => "missing file",
+    }
+}
+
+/// The entries of a tree, by their paths relative to the root
+type Entries<'a> = Peekable<Box<dyn Iterator<Item = (PathBuf, DirEntry)> + 'a>>;
+
+/// Walks both trees with the same `walk` settings and yields their differences in path
+/// order. The content of a directory which exists on one side only is not listed.
+///
+/// Both trees are walked side by side and files are compared as they are reached, so a
+/// caller which stops early does not walk or compare the rest.
+pub fn compare_dirs<'a>(
+    left: &'a Path,
+    right: &'a Path,
+    walk: impl Fn() -> Walk,
+) -> impl Iterator<Item = Change> + 'a {
+    let entries = |root: &'a Path| -> Entries<'a> {
+        let entries = walk().add_search_path(root).entries().filter_map(move |e| {
+            let relative = e.path().strip_prefix(root).ok()?.to_path_buf();
+            Some((relative, e))
+        });
+        (Box::new(entries) as Box<dyn Iterator<Item = _>>).peekable()
+    };
+    DirChanges {
+        left: entries(left),
+        right: entries(right),
+        skipped: None,
+    }
+}
+
+struct DirChanges<'a> {
+    left: Entries<'a>,
+    right: Entries<'a>,
+    /// A one-sided or replaced directory, whose content is not listed
+    skipped: Option<PathBuf>,
+}
+
+impl DirChanges<'_> {
+    /// Takes the entry with the lowest path, from both sides if both have it
+    fn next_pair(&mut self) -> Option<(PathBuf, Option<DirEntry>, Option<DirEntry>)> {
+        let order = match (self.left.peek(), self.right.peek()) {
+            (None, None) => return None,
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (Some((l, _)), Some((r, _))) => l.cmp(r),
+        };
+        Some(match order {
+            Ordering::Less => {
+                let (path, l) = self.left.next()?;
+                (path, Some(l), None)
+            }
+            Ordering::Greater => {
+                let (path, r) = self.right.next()?;
+                (path, None, Some(r))
+            }
+            Ordering::Equal => {
+                let (path, l) = self.left.next()?;
+                let (_, r) = self.right.next()?;
+                (path, Some(l), Some(r))
+            }
+        })
+    }
+}
+
+impl Iterator for DirChanges<'_> {
+    type Item = Change;
+
+    fn next(&mut self) -> Option<Change> {
+        while let Some((path, left, right)) = self.next_pair() {
+            if self.skipped.as_ref().is_some_and(|s| path.starts_with(s)) {
+                continue;
+            }
+            let change = match (left, right) {
+                (Some(l), None) => {
+                    if l.is_dir() {
+                        self.skipped = Some(path.clone());
+                    }
+                    Change::LeftOnly(path)
+                }
+                (None, Some(r)) => {
+                    if r.is_dir() {
+                        self.skipped = Some(path.clone());
+                    }
+                    Change::RightOnly(path)
+                }
+                (Some(l), Some(r)) => {
+                    let is_link = |e: &DirEntry| e.file_type().is_symlink();
+                    if l.is_dir() != r.is_dir()
+                        || l.is_file() != r.is_file()
+                        || is_link(&l) != is_link(&r)
+                    {
+                        // The content below a replaced directory is not compared
+                        if l.is_dir() || r.is_dir() {
+                            self.skipped = Some(path.clone());
+                        }
+                        Change::TypeChanged(path)
+                    } else if l.is_file() && !same_content(l.path(), r.path()).unwrap_or(false) {
+                        Change::Modified(path)
+                    } else if is_link(&l)
+                        && fs::read_link(l.path()).ok() != fs::read_link(r.path()).ok()
+                    {
+                        Change::LinkChanged(path)
+                    } else {
+                        continue;
+                    }
+                }
+                (None, None) => continue,
+            };
+            return Some(change);
+        }
+        None
+    }
+}
+
+/// Returns `true` if both files have the same content, the sizes are compared first
+/// and then the contents block by block.
+pub fn same_content(left: &Path, right: &Path) -> io::Result<bool> {
+    if left.metadata()?.len() != right.metadata()?.len() {
+        return Ok(false);
+    }
+
+    let mut left = File::open(left)?;
+    let mut right = File::open(right)?;
+    let mut left_block = vec![0; BLOCK_SIZE];
+    let mut right_block = vec![0; BLOCK_SIZE];
+    loop {
+        let n = read_block(&mut left, &mut left_block)?;
+        let m = read_block(&mut right, &mut right_block)?;
+        if left_block[..n] != right_block[..m] {
+            return Ok(false);
+        }
+        if n == 0 {
+            return Ok(true);
+        }
+    }
+}
+
+/// Fills the buffer as far as possible, returns less than the buffer size only at the end
+fn read_block<R: Read>(reader: &mut R, buf: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(filled)
+}
+
+/// Returns the path itself if it is a file, or all files below it if it is a directory.
+/// Symbolic links and special files have no content to compare.
+pub fn files_in(path: &Path, walk: Walk) -> Vec<PathBuf> {
+    match path.symlink_metadata() {
+        Ok(meta) if meta.is_dir() => {}
+        Ok(meta) if meta.is_file() => return vec![path.to_path_buf()],
+        _ => return vec![],
+    }
+    walk.add_search_path(path)
+        .entries()
+        .filter(|e| e.is_file())
+        .map(|e| e.path().to_path_buf())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    /// Creates the files below a new temporary directory
+    fn tree(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let root = env::temp_dir().join(format!("show-dir-test-{}-{}", std::process::id(), name));
+        let _ = fs::remove_dir_all(&root);
+        for (path, content) in files {
+            let path = root.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, content).unwrap();
+        }
+        root
+    }
+
+    #[test]
+    fn trees_are_compared_in_path_order() {
+        let left = tree(
+            "left",
+            &[
+                ("a", "1"),
+                ("d/x", ""),
+                ("d/y", ""),
+                ("same", "s"),
+                ("t", ""),
+            ],
+        );
+        let right = tree(
+            "right",
+            &[("a", "2"), ("n", ""), ("same", "s"), ("t/z", "")],
+        );
+        let changes: Vec<Change> = compare_dirs(&left, &right, Walk::new).collect();
+        assert_eq!(
+            changes,
+            [
+                Change::Modified("a".into()),
+                Change::LeftOnly("d".into()),
+                Change::RightOnly("n".into()),
+                Change::TypeChanged("t".into()),
+            ]
+        );
+        assert_eq!(type_name(&right.join("t")), "directory");
+        assert_eq!(type_name(&right.join("missing")), "missing file");
+        assert_eq!(files_in(&left.join("d"), Walk::new()).len(), 2);
+        assert_eq!(files_in(&left.join("a"), Walk::new()), [left.join("a")]);
+        fs::remove_dir_all(&left).unwrap();
+        fs::remove_dir_all(&right).unwrap();
+    }
+
+    #[test]
+    fn contents_are_compared_across_blocks() {
+        let block = "x".repeat(BLOCK_SIZE);
+        let root = tree(
+            "blocks",
+            &[
+                ("a", &format!("{}a", block)),
+                ("b", &format!("{}b", block)),
+                ("c", &format!("{}a", block)),
+                ("d", "short"),
+            ],
+        );
+        assert!(!same_content(&root.join("a"), &root.join("b")).unwrap());
+        assert!(same_content(&root.join("a"), &root.join("c")).unwrap());
+        assert!(!same_content(&root.join("a"), &root.join("d")).unwrap());
+        assert!(same_content(&root.join("a"), &root.join("missing")).is_err());
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
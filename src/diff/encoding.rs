@@ -0,0 +1,305 @@
+//! Text encoding and line ending detection.
+//!
+//! Inputs are decoded before they are compared, so a file saved as UTF-16 or with a BOM
+//! is not different in every line. Differences of the encoding and the line endings are
+//! reported once as findings instead.
+
+use ansi_term::Color::*;
+use memchr::memchr_iter;
+use std::borrow::Cow;
+use std::fmt;
+use std::io::{self, Write};
+
+/// The number of bytes which are looked at to detect UTF-16 without a BOM
+const SAMPLE_SIZE: usize = 1024;
+
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+const UTF16LE_BOM: &[u8] = b"\xFF\xFE";
+const UTF16BE_BOM: &[u8] = b"\xFE\xFF";
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Encoding {
+    Utf8,
+    Utf8Bom,
+    Utf16Le,
+    Utf16Be,
+    Utf16LeBom,
+    Utf16BeBom,
+    /// Any byte sequence which is not valid UTF-8
+    Latin1,
+}
+
+impl Encoding {
+    /// Detects the encoding by the BOM, or by the zero bytes of UTF-16 text. Text which is
+    /// not valid UTF-8 is Latin-1, which can decode any bytes.
+    pub fn detect(bytes: &[u8]) -> Self {
+        if bytes.starts_with(UTF8_BOM) {
+            return Encoding::Utf8Bom;
+        }
+        if bytes.starts_with(UTF16LE_BOM) {
+            return Encoding::Utf16LeBom;
+        }
+        if bytes.starts_with(UTF16BE_BOM) {
+            return Encoding::Utf16BeBom;
+        }
+        if let Some(encoding) = detect_utf16(bytes) {
+            return encoding;
+        }
+        match std::str::from_utf8(bytes) {
+            Ok(_) => Encoding::Utf8,
+            Err(_) => Encoding::Latin1,
+        }
+    }
+
+    #[inline]
+    pub fn is_utf16(self) -> bool {
+        matches!(
+            self,
+            Encoding::Utf16Le | Encoding::Utf16Be | Encoding::Utf16LeBom | Encoding::Utf16BeBom
+        )
+    }
+
+    /// Returns the size of the copy which `decode` makes, UTF-8 text is not copied. The
+    /// size of UTF-16 text is an upper bound, a unit takes at most 3 bytes in UTF-8.
+    pub fn decoded_size(self, bytes: &[u8]) -> u64 {
+        match self {
+            Encoding::Utf8 | Encoding::Utf8Bom => 0,
+            Encoding::Utf16Le | Encoding::Utf16Be | Encoding::Utf16LeBom | Encoding::Utf16BeBom => {
+                bytes.len() as u64 / 2 * 3
+            }
+            // Bytes above 0x7F take two bytes in UTF-8
+            Encoding::Latin1 => {
+                bytes.len() as u64 + bytes.iter().filter(|&&b| b >= 0x80).count() as u64
+            }
+        }
+    }
+
+    /// Encodes decoded text back into the bytes it was read from. Only Latin-1 has other
+    /// bytes than UTF-8, text read as UTF-16 stays in UTF-8 and has no BOM.
+    pub fn encode(self, text: &str) -> Cow<'_, [u8]> {
+        match self {
+            Encoding::Latin1 => Cow::Owned(text.chars().map(|c| c as u8).collect()),
+            _ => Cow::Borrowed(text.as_bytes()),
+        }
+    }
+
+    /// Decodes the bytes without their BOM, invalid UTF-16 is replaced by U+FFFD
+    pub fn decode(self, bytes: &[u8]) -> Cow<'_, str> {
+        match self {
+            Encoding::Utf8 => String::from_utf8_lossy(bytes),
+            Encoding::Utf8Bom => String::from_utf8_lossy(&bytes[UTF8_BOM.len()..]),
+            Encoding::Utf16Le | Encoding::Utf16Be | Encoding::Utf16LeBom | Encoding::Utf16BeBom => {
+                let bytes = match self {
+                    Encoding::Utf16LeBom | Encoding::Utf16BeBom => &bytes[UTF16LE_BOM.len()..],
+                    _ => bytes,
+                };
+                let little_endian = matches!(self, Encoding::Utf16Le | Encoding::Utf16LeBom);
+                let units = bytes.chunks(2).map(|pair| match pair {
+                    [low, high] if little_endian => u16::from_le_bytes([*low, *high]),
+                    [high, low] => u16::from_be_bytes([*high, *low]),
+                    // A truncated last unit
+                    _ => 0xFFFD,
+                });
+                Cow::Owned(
+                    char::decode_utf16(units)
+                        .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+                        .collect(),
+                )
+            }
+            Encoding::Latin1 => Cow::Owned(bytes.iter().map(|&b| b as char).collect()),
+        }
+    }
+}
+
+impl fmt::Display for Encoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Encoding::Utf8 => "UTF-8",
+            Encoding::Utf8Bom => "UTF-8 with BOM",
+            Encoding::Utf16Le => "UTF-16LE",
+            Encoding::Utf16Be => "UTF-16BE",
+            Encoding::Utf16LeBom => "UTF-16LE with BOM",
+            Encoding::Utf16BeBom => "UTF-16BE with BOM",
+            Encoding::Latin1 => "Latin-1",
+        })
+    }
+}
+
+/// Text in UTF-16 without a BOM has a zero byte in most units if it is mostly ASCII,
+/// always at the same side of the unit
+fn detect_utf16(bytes: &[u8]) -> Option<Encoding> {
+    if bytes.len() < 2 || !bytes.len().is_multiple_of(2) {
+        return None;
+    }
+    let sample = &bytes[..bytes.len().min(SAMPLE_SIZE)];
+    let units = sample.len() / 2;
+    let (mut even_zeros, mut odd_zeros) = (0, 0);
+    for pair in sample.chunks_exact(2) {
+        even_zeros += (pair[0] == 0) as usize;
+        odd_zeros += (pair[1] == 0) as usize;
+    }
+    match (even_zeros, odd_zeros) {
+        (0, odd) if odd * 2 >= units => Some(Encoding::Utf16Le),
+        (even, 0) if even * 2 >= units => Some(Encoding::Utf16Be),
+        _ => None,
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum LineEnding {
+    Lf,
+    CrLf,
+    Mixed,
+    /// A single line without a terminator
+    Unknown,
+}
+
+impl LineEnding {
+    pub fn detect(text: &str) -> Self {
+        let (mut lf, mut crlf) = (0, 0);
+        for end in memchr_iter(b'\n', text.as_bytes()) {
+            if end > 0 && text.as_bytes()[end - 1] == b'\r' {
+                crlf += 1;
+            } else {
+                lf += 1;
+            }
+        }
+        match (lf, crlf) {
+            (0, 0) => LineEnding::Unknown,
+            (_, 0) => LineEnding::Lf,
+            (0, _) => LineEnding::CrLf,
+            _ => LineEnding::Mixed,
+        }
+    }
+}
+
+impl fmt::Display for LineEnding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            LineEnding::Lf => "LF",
+            LineEnding::CrLf => "CRLF",
+            LineEnding::Mixed => "mixed",
+            LineEnding::Unknown => "unknown",
+        })
+    }
+}
+
+/// The differences of two inputs which are not differences of their lines
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Findings {
+    pub encoding: Option<(Encoding, Encoding)>,
+    pub line_ending: Option<(LineEnding, LineEnding)>,
+}
+
+impl Findings {
+    /// Compares the encodings and the line endings of the inputs, the line endings are not
+    /// compared if `strip_trailing_cr` makes them equal anyway
+    pub fn new(old: (Encoding, &str), new: (Encoding, &str), strip_trailing_cr: bool) -> Self {
+        let encoding = Some((old.0, new.0)).filter(|(o, n)| o != n);
+        let line_ending = if strip_trailing_cr {
+            None
+        } else {
+            let (old_ending, new_ending) = (LineEnding::detect(old.1), LineEnding::detect(new.1));
+            Some((old_ending, new_ending))
+                .filter(|(o, n)| o != n && *o != LineEnding::Unknown && *n != LineEnding::Unknown)
+        };
+        Self {
+            encoding,
+            line_ending,
+        }
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.encoding.is_none() && self.line_ending.is_none()
+    }
+
+    /// Returns `true` if the lines must be compared without their carriage returns
+    #[inline]
+    pub fn line_endings_differ(&self) -> bool {
+        self.line_ending.is_some()
+    }
+
+    pub fn write<W: Write>(&self, out: &mut W, color: bool) -> io::Result<()> {
+        let mut lines = Vec::new();
+        if let Some((old, new)) = self.encoding {
+            lines.push(format!("Encoding changed from {} to {}", old, new));
+        }
+        if let Some((old, new)) = self.line_ending {
+            lines.push(format!("Line endings changed from {} to {}", old, new));
+        }
+        for line in lines {
+            if color {
+                writeln!(out, "{}", Yellow.paint(line))?;
+            } else {
+                writeln!(out, "{}", line)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodings_are_detected() {
+        assert_eq!(Encoding::detect(b"plain"), Encoding::Utf8);
+        assert_eq!(Encoding::detect(b"\xEF\xBB\xBFbom"), Encoding::Utf8Bom);
+        assert_eq!(Encoding::detect(b"\xFF\xFEa\0"), Encoding::Utf16LeBom);
+        assert_eq!(Encoding::detect(b"\xFE\xFF\0a"), Encoding::Utf16BeBom);
+        assert_eq!(Encoding::detect(b"a\0b\0"), Encoding::Utf16Le);
+        assert_eq!(Encoding::detect(b"\0a\0b"), Encoding::Utf16Be);
+        assert_eq!(Encoding::detect(b"caf\xE9"), Encoding::Latin1);
+    }
+
+    #[test]
+    fn text_is_decoded_without_bom() {
+        assert_eq!(Encoding::Utf8Bom.decode(b"\xEF\xBB\xBFbom"), "bom");
+        assert_eq!(Encoding::Utf16LeBom.decode(b"\xFF\xFEa\0\xE9\0"), "a\u{e9}");
+        assert_eq!(Encoding::Utf16BeBom.decode(b"\xFE\xFF\0a\0\xE9"), "a\u{e9}");
+        assert_eq!(Encoding::Utf16Le.decode(b"a\0b"), "a\u{fffd}");
+        assert_eq!(Encoding::Latin1.decode(b"caf\xE9"), "caf\u{e9}");
+    }
+
+    #[test]
+    fn latin1_is_encoded_into_its_bytes() {
+        let text = Encoding::Latin1.decode(b"caf\xE9\n");
+        assert_eq!(Encoding::Latin1.encode(&text), &b"caf\xE9\n"[..]);
+        assert_eq!(
+            Encoding::Utf16Le.encode("caf\u{e9}"),
+            "caf\u{e9}".as_bytes()
+        );
+        assert_eq!(Encoding::Latin1.decoded_size(b"caf\xE9"), 5);
+    }
+
+    #[test]
+    fn findings_report_changes_once() {
+        let findings = Findings::new(
+            (Encoding::Utf16LeBom, "a\r\n"),
+            (Encoding::Utf8, "a\n"),
+            false,
+        );
+        let mut out = Vec::new();
+        findings.write(&mut out, false).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "\
+Encoding changed from UTF-16LE with BOM to UTF-8
+Line endings changed from CRLF to LF
+"
+        );
+        // A single line without terminator has no line ending to compare
+        let findings = Findings::new((Encoding::Utf8, "a"), (Encoding::Utf8, "a\r\n"), false);
+        assert!(findings.is_empty());
+        assert_eq!(LineEnding::detect("a\nb\r\n"), LineEnding::Mixed);
+    }
+
+    #[test]
+    fn stripped_carriage_returns_are_no_finding() {
+        let findings = Findings::new((Encoding::Utf8, "a\r\n"), (Encoding::Utf8, "a\n"), true);
+        assert!(!findings.line_endings_differ());
+        assert!(findings.is_empty());
+    }
+}
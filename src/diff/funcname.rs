@@ -0,0 +1,119 @@
+//! Function headers of hunks, the `@@` line of a hunk names the function or section
+//! which encloses it, like git does with its `diff=<driver>` patterns.
+//!
+//! The language is chosen by the file extension. The nearest line above the hunk which
+//! matches a pattern of the language is the header.
+
+use regex::Regex;
+use std::path::Path;
+
+/// The longest header, longer lines are cut at a character boundary
+const MAX_HEADER_CHARS: usize = 80;
+
+const RUST_PATTERNS: &[&str] = &[
+    r#"^\s*(pub(\([^)]+\))?\s+)?((async|const|unsafe|default|extern(\s+"[^"]+")?)\s+)*fn\s+\w+"#,
+    r#"^\s*(pub(\([^)]+\))?\s+)?(unsafe\s+)?(impl|trait|struct|enum|union|mod)\b[^;]*$"#,
+    r#"^\s*macro_rules!\s*\w+"#,
+];
+
+const PYTHON_PATTERNS: &[&str] = &[r#"^\s*((async\s+)?def|class)\s+\w+"#];
+
+const C_PATTERNS: &[&str] = &[
+    // A definition starts at the first column, statements are indented
+    r#"^[A-Za-z_][^;]*\([^;]*$"#,
+    r#"^(struct|union|enum|class|namespace)\b[^;]*$"#,
+];
+
+const MARKDOWN_PATTERNS: &[&str] = &[r#"^#{1,6}\s+\S"#];
+
+/// Finds the function or section line which encloses a line
+pub struct FunctionMatcher {
+    patterns: Vec<Regex>,
+}
+
+impl FunctionMatcher {
+    /// Returns the matcher of the language of the file, or `None` if it is not known
+    pub fn for_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        let patterns = match extension.as_str() {
+            "rs" => RUST_PATTERNS,
+            "py" | "pyi" => PYTHON_PATTERNS,
+            "c" | "h" | "cc" | "cpp" | "cxx" | "hh" | "hpp" | "hxx" => C_PATTERNS,
+            "md" | "markdown" => MARKDOWN_PATTERNS,
+            _ => return None,
+        };
+        Some(Self {
+            patterns: patterns
+                .iter()
+                .map(|p| Regex::new(p).expect("function patterns are valid"))
+                .collect(),
+        })
+    }
+
+    /// Returns the nearest line above `index` which starts a function or section,
+    /// without its line terminator
+    pub fn find(&self, lines: &[&str], index: usize) -> Option<String> {
+        lines[..index.min(lines.len())]
+            .iter()
+            .rev()
+            .map(|line| line.trim_end())
+            .find(|line| self.patterns.iter().any(|re| re.is_match(line)))
+            .map(|line| line.chars().take(MAX_HEADER_CHARS).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(path: &str, text: &str, index: usize) -> Option<String> {
+        let lines: Vec<&str> = text.split_inclusive('\n').collect();
+        FunctionMatcher::for_path(Path::new(path))?.find(&lines, index)
+    }
+
+    #[test]
+    fn nearest_function_is_the_header() {
+        let rust = "\
+pub(crate) struct A;
+
+impl A {
+    pub async fn run(&self) {
+        let x = 1;
+        x
+    }
+}
+";
+        assert_eq!(
+            header("a.rs", rust, 5).as_deref(),
+            Some("    pub async fn run(&self) {")
+        );
+        assert_eq!(header("a.rs", rust, 3).as_deref(), Some("impl A {"));
+        assert_eq!(header("a.rs", rust, 0), None);
+
+        let python = "class A:\n    def run(self):\n        pass\n";
+        assert_eq!(
+            header("a.PY", python, 2).as_deref(),
+            Some("    def run(self):")
+        );
+    }
+
+    #[test]
+    fn statements_are_no_c_functions() {
+        let c = "int main(void)\n{\n    call(1);\n    return 0;\n}\n";
+        assert_eq!(header("a.c", c, 4).as_deref(), Some("int main(void)"));
+        assert_eq!(
+            header("a.md", "# Title\ntext\n## Usage\ntext\n", 4).as_deref(),
+            Some("## Usage")
+        );
+        assert_eq!(header("a.txt", "fn a() {}\n", 1), None);
+    }
+
+    #[test]
+    fn long_headers_are_cut() {
+        let line = format!("fn {}() {{}}\n", "a".repeat(100));
+        assert_eq!(
+            header("a.rs", &line, 1).map(|h| h.chars().count()),
+            Some(MAX_HEADER_CHARS)
+        );
+    }
+}
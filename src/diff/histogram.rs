@@ -0,0 +1,165 @@
+//! Histogram diff, an extension of patience diff which is also used by git.
+//!
+//! Instead of unique lines, the common region which contains the least frequent line
+//! is used to divide the problem. Ranges where every line is too frequent are compared
+//! with Myers.
+
+use super::myers;
+use super::ops::{common_prefix_len, common_suffix_len, Capture};
+use std::collections::HashMap;
+use std::hash::Hash;
+
+/// Lines which occur more often than this on the old side are never used as a split
+const MAX_CHAIN_LEN: usize = 64;
+
+/// A common region of both sides
+struct Region {
+    old_index: usize,
+    new_index: usize,
+    len: usize,
+    /// The lowest number of occurrences on the old side of the lines in this region
+    count: usize,
+}
+
+pub fn diff_into<T: Hash + Eq>(
+    old: &[T],
+    old_start: usize,
+    new: &[T],
+    new_start: usize,
+    capture: &mut Capture,
+) {
+    let prefix = common_prefix_len(old, new);
+    capture.equal(old_start, new_start, prefix);
+    let (old, new) = (&old[prefix..], &new[prefix..]);
+    let (old_start, new_start) = (old_start + prefix, new_start + prefix);
+
+    let suffix = common_suffix_len(old, new);
+    let (old, new) = (&old[..old.len() - suffix], &new[..new.len() - suffix]);
+
+    if old.is_empty() || new.is_empty() {
+        capture.delete(old_start, old.len(), new_start);
+        capture.insert(old_start + old.len(), new_start, new.len());
+    } else if let Some(region) = find_region(old, new) {
+        let (x, y) = (region.old_index, region.new_index);
+        diff_into(&old[..x], old_start, &new[..y], new_start, capture);
+        capture.equal(old_start + x, new_start + y, region.len);
+        diff_into(
+            &old[x + region.len..],
+            old_start + x + region.len,
+            &new[y + region.len..],
+            new_start + y + region.len,
+            capture,
+        );
+    } else {
+        myers::diff_into(old, old_start, new, new_start, false, capture);
+    }
+
+    capture.equal(old_start + old.len(), new_start + new.len(), suffix);
+}
+
+/// Finds the common region with the least frequent line, the longer one wins a tie
+fn find_region<T: Hash + Eq>(old: &[T], new: &[T]) -> Option<Region> {
+    let mut occurrences: HashMap<&T, Vec<usize>> = HashMap::new();
+    for (i, line) in old.iter().enumerate() {
+        occurrences.entry(line).or_default().push(i);
+    }
+
+    let mut best: Option<Region> = None;
+    let mut j = 0;
+    while j < new.len() {
+        let mut next_j = j + 1;
+        let positions = match occurrences.get(&new[j]) {
+            Some(p) if p.len() <= MAX_CHAIN_LEN => p,
+            _ => {
+                j = next_j;
+                continue;
+            }
+        };
+
+        for &i in positions {
+            // Extend the match in both directions
+            let (mut start_i, mut start_j) = (i, j);
+            while start_i > 0 && start_j > 0 && old[start_i - 1] == new[start_j - 1] {
+                start_i -= 1;
+                start_j -= 1;
+            }
+            let (mut end_i, mut end_j) = (i + 1, j + 1);
+            while end_i < old.len() && end_j < new.len() && old[end_i] == new[end_j] {
+                end_i += 1;
+                end_j += 1;
+            }
+
+            let count = old[start_i..end_i]
+                .iter()
+                .filter_map(|line| occurrences.get(line).map(Vec::len))
+                .min()
+                .unwrap_or(usize::MAX);
+            let len = end_i - start_i;
+            let better = match &best {
+                None => true,
+                Some(b) => count < b.count || (count == b.count && len > b.len),
+            };
+            if better {
+                best = Some(Region {
+                    old_index: start_i,
+                    new_index: start_j,
+                    len,
+                    count,
+                });
+            }
+            next_j = next_j.max(end_j);
+        }
+        j = next_j;
+    }
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diff::ops::{apply, DiffOp};
+
+    fn diff(old: &[&str], new: &[&str]) -> Vec<DiffOp> {
+        let mut capture = Capture::new();
+        diff_into(old, 0, new, 0, &mut capture);
+        capture.into_ops()
+    }
+
+    #[test]
+    fn script_turns_old_into_new() {
+        let cases: &[(&[&str], &[&str])] = &[
+            (&[], &["a"]),
+            (&["a"], &[]),
+            (&["a", "b", "c"], &["c", "b", "a"]),
+            (
+                &["}", "fn a", "}", "fn b", "}"],
+                &["}", "fn b", "}", "fn c", "}"],
+            ),
+            (&["x", "a", "x", "b", "x"], &["b", "x", "a", "x"]),
+        ];
+        for (old, new) in cases {
+            assert_eq!(apply(&diff(old, new), old, new), *new);
+        }
+    }
+
+    #[test]
+    fn region_of_the_least_frequent_line_wins() {
+        // `b` and `c` occur once, the region around `b` is longer
+        let region = find_region(&["a", "b", "a", "c"], &["c", "a", "b"]).unwrap();
+        assert_eq!((region.old_index, region.new_index), (0, 1));
+        assert_eq!((region.len, region.count), (2, 1));
+    }
+
+    #[test]
+    fn too_frequent_lines_fall_back_to_myers() {
+        let old: Vec<&str> = ["a"; MAX_CHAIN_LEN + 1]
+            .iter()
+            .chain(&["b"; MAX_CHAIN_LEN + 1])
+            .copied()
+            .collect();
+        let new: Vec<&str> = old.iter().rev().copied().collect();
+        assert!(find_region(&old, &new).is_none());
+        assert_eq!(diff(&old, &new), myers::diff(&old, &new));
+        assert_eq!(apply(&diff(&old, &new), &old, &new), new);
+    }
+}
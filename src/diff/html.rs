@@ -0,0 +1,386 @@
+//! HTML report of `show diff --format html`.
+//!
+//! The report is a single file with its styles inlined, it references no scripts, fonts
+//! or images, so it can be attached to a ticket and opened anywhere. Both the unified and
+//! the side-by-side view are included, a switch at the top shows one of them.
+
+use super::encoding::Findings;
+use super::hunk::Hunk;
+use super::inline::{changed_ranges, paired_lines, Highlights};
+use super::ops::DiffOp;
+use super::stat::FileStat;
+use super::unified::format_range;
+use std::fmt::Write as _;
+use std::io::{self, Write};
+
+const STYLE: &str = r#"
+body { font-family: sans-serif; margin: 0 2em 2em; color: #24292f; }
+nav ul { list-style: none; padding: 0; }
+nav li { font-family: monospace; margin: 0.2em 0; }
+section { margin: 2em 0; border: 1px solid #d0d7de; border-radius: 6px; overflow: hidden; }
+h2 { font-size: 1em; font-family: monospace; margin: 0; padding: 0.6em 1em; background: #f6f8fa; border-bottom: 1px solid #d0d7de; }
+.note { margin: 0; padding: 0.4em 1em; background: #fff8c5; font-size: 0.9em; }
+table { border-collapse: collapse; width: 100%; font-family: monospace; font-size: 0.85em; table-layout: fixed; }
+td { padding: 0 0.5em; vertical-align: top; white-space: pre-wrap; word-break: break-all; }
+td.num { width: 4em; color: #6e7781; text-align: right; user-select: none; }
+td.marker { width: 1em; user-select: none; }
+tr.hunk td { background: #ddf4ff; color: #57606a; padding: 0.2em 0.5em; }
+.del { background: #ffebe9; }
+.add { background: #e6ffec; }
+.del mark { background: #ff8182; }
+.add mark { background: #abf2bc; }
+.empty { background: #f6f8fa; }
+span.add, span.del { padding: 0 0.3em; }
+label { margin-right: 1em; }
+#view-unified:checked ~ main .split, #view-split:checked ~ main .unified { display: none; }
+"#;
+
+/// The view which is shown first
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum HtmlView {
+    Unified,
+    SideBySide,
+}
+
+/// Collects the files of the report, the document is written by `finish` because the
+/// index comes before the files
+pub struct HtmlWriter<W: Write> {
+    out: W,
+    title: String,
+    view: HtmlView,
+    index: bool,
+    nav: String,
+    body: String,
+    files: usize,
+}
+
+impl<W: Write> HtmlWriter<W> {
+    pub fn new(out: W, title: &str) -> Self {
+        Self {
+            out,
+            title: title.to_string(),
+            view: HtmlView::Unified,
+            index: false,
+            nav: String::new(),
+            body: String::new(),
+            files: 0,
+        }
+    }
+
+    /// Sets the view which is shown first
+    pub fn with_view(mut self, view: HtmlView) -> Self {
+        self.view = view;
+        self
+    }
+
+    /// Lists the files with links at the top, for directory diffs
+    pub fn with_index(mut self, index: bool) -> Self {
+        self.index = index;
+        self
+    }
+
+    /// Adds a text file, the ops of the hunks index `old_lines` and `new_lines`
+    pub fn write_file(
+        &mut self,
+        name: &str,
+        hunks: &[Hunk],
+        old_lines: &[&str],
+        new_lines: &[&str],
+        findings: &Findings,
+    ) {
+        self.begin_file(&FileStat::from_hunks(name.to_string(), hunks));
+        if let Some((old, new)) = findings.encoding {
+            let note = format!("Encoding changed from {} to {}", old, new);
+            let _ = writeln!(self.body, "<p class=\"note\">{}</p>", escape(&note));
+        }
+        if let Some((old, new)) = findings.line_ending {
+            let note = format!("Line endings changed from {} to {}", old, new);
+            let _ = writeln!(self.body, "<p class=\"note\">{}</p>", escape(&note));
+        }
+
+        let mut unified = String::new();
+        let mut split = String::new();
+        for hunk in hunks {
+            let header = format!(
+                "@@ -{} +{} @@",
+                format_range(hunk.old_range()),
+                format_range(hunk.new_range())
+            );
+            let _ = writeln!(
+                unified,
+                "<tr class=\"hunk\"><td colspan=\"4\">{}</td></tr>",
+                escape(&header)
+            );
+            let _ = writeln!(
+                split,
+                "<tr class=\"hunk\"><td colspan=\"6\">{}</td></tr>",
+                escape(&header)
+            );
+            for op in hunk.ops() {
+                write_op(&mut unified, &mut split, op, old_lines, new_lines);
+            }
+        }
+        let _ = writeln!(
+            self.body,
+            "<table class=\"unified\">\n{}</table>\n<table class=\"split\">\n{}</table>",
+            unified, split
+        );
+        self.body.push_str("</section>\n");
+    }
+
+    /// Adds a binary file, which has no lines to show
+    pub fn write_binary(&mut self, name: &str, old_size: usize, new_size: usize) {
+        self.begin_file(&FileStat::binary(name.to_string(), old_size, new_size));
+        self.body
+            .push_str("<p class=\"note\">Binary files differ</p>\n</section>\n");
+    }
+
+    /// Adds the entry of the index and opens the section of a file
+    fn begin_file(&mut self, stat: &FileStat) {
+        self.files += 1;
+        let id = self.files;
+        let name = escape(stat.name());
+        let _ = writeln!(
+            self.nav,
+            "<li><a href=\"#file-{}\">{}</a> <span class=\"add\">+{}</span> \
+             <span class=\"del\">-{}</span></li>",
+            id,
+            name,
+            stat.insertions(),
+            stat.deletions()
+        );
+        let _ = writeln!(self.body, "<section id=\"file-{}\">\n<h2>{}</h2>", id, name);
+    }
+
+    pub fn finish(mut self) -> io::Result<()> {
+        let (unified, split) = match self.view {
+            HtmlView::Unified => (" checked", ""),
+            HtmlView::SideBySide => ("", " checked"),
+        };
+        write!(
+            self.out,
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n\
+             <style>{}</style>\n</head>\n<body>\n<h1>{}</h1>\n",
+            escape(&self.title),
+            STYLE,
+            escape(&self.title)
+        )?;
+        writeln!(
+            self.out,
+            "<input type=\"radio\" name=\"view\" id=\"view-unified\"{}>\
+             <label for=\"view-unified\">Unified</label>\n\
+             <input type=\"radio\" name=\"view\" id=\"view-split\"{}>\
+             <label for=\"view-split\">Side by side</label>",
+            unified, split
+        )?;
+        writeln!(self.out, "<main>")?;
+        if self.files == 0 {
+            writeln!(self.out, "<p>No differences</p>")?;
+        } else if self.index {
+            writeln!(self.out, "<nav>\n<ul>\n{}</ul>\n</nav>", self.nav)?;
+        }
+        write!(self.out, "{}", self.body)?;
+        writeln!(self.out, "</main>\n</body>\n</html>")?;
+        self.out.flush()
+    }
+}
+
+/// Adds the rows of an op to the unified and the side-by-side table
+fn write_op(
+    unified: &mut String,
+    split: &mut String,
+    op: &DiffOp,
+    old_lines: &[&str],
+    new_lines: &[&str],
+) {
+    let (old_range, new_range) = (op.old_range(), op.new_range());
+    if op.is_equal() {
+        for (i, j) in old_range.zip(new_range) {
+            let code = code(old_lines[i], None);
+            let _ = writeln!(
+                unified,
+                "<tr>{}{}<td class=\"marker\"></td><td>{}</td></tr>",
+                number(Some(i)),
+                number(Some(j)),
+                code
+            );
+            let _ = writeln!(
+                split,
+                "<tr>{}<td class=\"marker\"></td><td>{}</td>{}<td class=\"marker\"></td><td>{}</td></tr>",
+                number(Some(i)),
+                code,
+                number(Some(j)),
+                code
+            );
+        }
+        return;
+    }
+
+    let mut old_highlights: Vec<Option<Highlights>> = vec![None; old_range.len()];
+    let mut new_highlights: Vec<Option<Highlights>> = vec![None; new_range.len()];
+    for (i, j) in paired_lines(op) {
+        if let Some((o, n)) = changed_ranges(old_lines[i], new_lines[j]) {
+            old_highlights[i - old_range.start] = Some(o);
+            new_highlights[j - new_range.start] = Some(n);
+        }
+    }
+    let old_code: Vec<String> = old_range
+        .clone()
+        .zip(&old_highlights)
+        .map(|(i, h)| code(old_lines[i], h.as_ref()))
+        .collect();
+    let new_code: Vec<String> = new_range
+        .clone()
+        .zip(&new_highlights)
+        .map(|(j, h)| code(new_lines[j], h.as_ref()))
+        .collect();
+
+    for (k, code) in old_code.iter().enumerate() {
+        let _ = writeln!(
+            unified,
+            "<tr class=\"del\">{}{}<td class=\"marker\">-</td><td>{}</td></tr>",
+            number(Some(old_range.start + k)),
+            number(None),
+            code
+        );
+    }
+    for (k, code) in new_code.iter().enumerate() {
+        let _ = writeln!(
+            unified,
+            "<tr class=\"add\">{}{}<td class=\"marker\">+</td><td>{}</td></tr>",
+            number(None),
+            number(Some(new_range.start + k)),
+            code
+        );
+    }
+
+    for k in 0..old_code.len().max(new_code.len()) {
+        let side = |class: &str, marker: char, start: usize, code: Option<&String>| match code {
+            Some(code) => format!(
+                "{}<td class=\"marker {}\">{}</td><td class=\"{}\">{}</td>",
+                number(Some(start + k)),
+                class,
+                marker,
+                class,
+                code
+            ),
+            None => "<td class=\"num empty\"></td><td class=\"marker empty\"></td>\
+                     <td class=\"empty\"></td>"
+                .to_string(),
+        };
+        let _ = writeln!(
+            split,
+            "<tr>{}{}</tr>",
+            side("del", '-', old_range.start, old_code.get(k)),
+            side("add", '+', new_range.start, new_code.get(k))
+        );
+    }
+}
+
+/// Returns the cell of a 0-based line index, as a 1-based line number
+fn number(index: Option<usize>) -> String {
+    match index {
+        Some(i) => format!("<td class=\"num\">{}</td>", i + 1),
+        None => "<td class=\"num\"></td>".to_string(),
+    }
+}
+
+/// Returns the escaped line without its terminator, the highlighted parts are marked
+fn code(line: &str, highlights: Option<&Highlights>) -> String {
+    let content = line.strip_suffix('\n').unwrap_or(line);
+    let content = content.strip_suffix('\r').unwrap_or(content);
+    let highlights = match highlights {
+        Some(highlights) => highlights,
+        None => return escape(content),
+    };
+    let mut html = String::with_capacity(content.len() * 2);
+    let mut last = 0;
+    for range in highlights {
+        // The highlights are ranges of the line with its `\r`, which is not shown
+        let (start, end) = (range.start.min(content.len()), range.end.min(content.len()));
+        html.push_str(&escape(&content[last..start]));
+        let _ = write!(html, "<mark>{}</mark>", escape(&content[start..end]));
+        last = end;
+    }
+    html.push_str(&escape(&content[last..]));
+    html
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diff::hunk::group_hunks;
+    use crate::diff::myers;
+
+    fn report(writer: impl FnOnce(&mut HtmlWriter<&mut Vec<u8>>)) -> String {
+        let mut out = Vec::new();
+        let mut html = HtmlWriter::new(&mut out, "a <b>").with_index(true);
+        writer(&mut html);
+        html.finish().unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn changed_lines_have_marks() {
+        let (old, new) = (["a<b\r\n"], ["a<c\r\n", "d\n"]);
+        let op = DiffOp::from_ranges(0..1, 0..2);
+        let (mut unified, mut split) = (String::new(), String::new());
+        write_op(&mut unified, &mut split, &op, &old, &new);
+        assert_eq!(
+            unified,
+            "\
+<tr class=\"del\"><td class=\"num\">1</td><td class=\"num\"></td><td class=\"marker\">-</td><td>a&lt;<mark>b</mark></td></tr>
+<tr class=\"add\"><td class=\"num\"></td><td class=\"num\">1</td><td class=\"marker\">+</td><td>a&lt;<mark>c</mark></td></tr>
+<tr class=\"add\"><td class=\"num\"></td><td class=\"num\">2</td><td class=\"marker\">+</td><td>d</td></tr>
+"
+        );
+        // The side without a line has an empty cell
+        assert_eq!(split.lines().count(), 2);
+        assert!(split.lines().nth(1).unwrap().starts_with(
+            "<tr><td class=\"num empty\"></td><td class=\"marker empty\"></td><td class=\"empty\"></td>"
+        ));
+    }
+
+    #[test]
+    fn report_lists_files_in_the_index() {
+        let (old, new) = (["a\n", "b\n"], ["a\n", "c\n"]);
+        let hunks = group_hunks(&myers::diff(&old, &new), 3);
+        let html = report(|html| {
+            html.write_file("x&y.txt", &hunks, &old, &new, &Findings::default());
+            html.write_binary("logo.png", 1, 2);
+        });
+        assert!(html.contains("<title>a &lt;b&gt;</title>"));
+        assert!(html.contains(
+            "<li><a href=\"#file-1\">x&amp;y.txt</a> <span class=\"add\">+1</span> \
+             <span class=\"del\">-1</span></li>"
+        ));
+        assert!(html.contains(
+            "<section id=\"file-2\">\n<h2>logo.png</h2>\n<p class=\"note\">Binary files differ</p>"
+        ));
+        assert!(html.contains("<tr class=\"hunk\"><td colspan=\"4\">@@ -1,2 +1,2 @@</td></tr>"));
+        assert!(!html.contains("<script") && !html.contains("src="));
+    }
+
+    #[test]
+    fn empty_report_says_so() {
+        let html = report(|_| {});
+        assert!(html.contains("<main>\n<p>No differences</p>\n</main>"));
+        assert!(html.contains("id=\"view-unified\" checked>"));
+        assert_eq!(escape("'\""), "&#39;&quot;");
+    }
+}
@@ -0,0 +1,137 @@
+use super::ops::DiffOp;
+use std::ops::Range;
+
+/// The default number of context lines around a change
+pub const DEFAULT_CONTEXT: usize = 3;
+
+/// A group of changes together with its surrounding context
+#[derive(Clone, Debug)]
+pub struct Hunk {
+    ops: Vec<DiffOp>,
+}
+
+impl Hunk {
+    #[inline]
+    pub fn ops(&self) -> &[DiffOp] {
+        &self.ops
+    }
+
+    /// Returns the range of the old sequence covered by this hunk
+    pub fn old_range(&self) -> Range<usize> {
+        let first = self.ops.first().map_or(0, |op| op.old_range().start);
+        let last = self.ops.last().map_or(0, |op| op.old_range().end);
+        first..last
+    }
+
+    /// Returns the range of the new sequence covered by this hunk
+    pub fn new_range(&self) -> Range<usize> {
+        let first = self.ops.first().map_or(0, |op| op.new_range().start);
+        let last = self.ops.last().map_or(0, |op| op.new_range().end);
+        first..last
+    }
+}
+
+/// Splits an edit script into hunks, each change keeps at most `context` equal
+/// lines before and after it. Changes which are closer than `2 * context` lines
+/// share the same hunk.
+pub fn group_hunks(ops: &[DiffOp], context: usize) -> Vec<Hunk> {
+    let mut hunks = Vec::new();
+    let mut current: Vec<DiffOp> = Vec::new();
+
+    for (i, op) in ops.iter().enumerate() {
+        let (old_index, new_index, len) = match *op {
+            DiffOp::Equal {
+                old_index,
+                new_index,
+                len,
+            } => (old_index, new_index, len),
+            _ => {
+                current.push(*op);
+                continue;
+            }
+        };
+
+        let is_last = i + 1 == ops.len();
+
+        if current.is_empty() {
+            // Leading context of the next hunk
+            if !is_last {
+                let keep = len.min(context);
+                if keep > 0 {
+                    current.push(DiffOp::Equal {
+                        old_index: old_index + len - keep,
+                        new_index: new_index + len - keep,
+                        len: keep,
+                    });
+                }
+            }
+        } else if is_last || len > 2 * context {
+            // Trailing context of the current hunk
+            let keep = len.min(context);
+            if keep > 0 {
+                current.push(DiffOp::Equal {
+                    old_index,
+                    new_index,
+                    len: keep,
+                });
+            }
+            hunks.push(Hunk {
+                ops: std::mem::take(&mut current),
+            });
+
+            // Leading context of the next hunk
+            if !is_last {
+                let keep = len.min(context);
+                if keep > 0 {
+                    current.push(DiffOp::Equal {
+                        old_index: old_index + len - keep,
+                        new_index: new_index + len - keep,
+                        len: keep,
+                    });
+                }
+            }
+        } else {
+            current.push(*op);
+        }
+    }
+
+    if current.iter().any(|op| !op.is_equal()) {
+        hunks.push(Hunk { ops: current });
+    }
+    hunks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diff::myers;
+
+    /// The old ranges of the hunks as `(start, end)` pairs
+    fn hunks(context: usize) -> Vec<(usize, usize)> {
+        let old: Vec<usize> = (0..10).collect();
+        let mut new = old.clone();
+        new[2] = 20;
+        new[8] = 80;
+        group_hunks(&myers::diff(&old, &new), context)
+            .iter()
+            .map(|hunk| (hunk.old_range().start, hunk.old_range().end))
+            .collect()
+    }
+
+    #[test]
+    fn close_changes_share_a_hunk() {
+        assert_eq!(hunks(3), [(0, 10)]);
+    }
+
+    #[test]
+    fn distant_changes_are_split() {
+        assert_eq!(hunks(2), [(0, 5), (6, 10)]);
+        assert_eq!(hunks(1), [(1, 4), (7, 10)]);
+        assert_eq!(hunks(0), [(2, 3), (8, 9)]);
+    }
+
+    #[test]
+    fn equal_sequences_have_no_hunks() {
+        assert!(group_hunks(&myers::diff(&[1, 2], &[1, 2]), 3).is_empty());
+    }
+}
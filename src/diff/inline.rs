@@ -0,0 +1,96 @@
+use super::myers;
+use super::ops::DiffOp;
+use std::ops::Range;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// The byte ranges of a line which differ from its paired line
+pub type Highlights = Vec<Range<usize>>;
+
+/// Compares two lines grapheme by grapheme, so combining marks and emoji are never split,
+/// and returns the changed byte ranges of the old and the new line.
+///
+/// Returns `None` if the lines have nothing in common, highlighting the whole line
+/// would not help in that case.
+pub fn changed_ranges(old: &str, new: &str) -> Option<(Highlights, Highlights)> {
+    let old_graphemes = graphemes(old);
+    let new_graphemes = graphemes(new);
+    let old_tokens: Vec<&str> = old_graphemes.iter().map(|(_, g)| *g).collect();
+    let new_tokens: Vec<&str> = new_graphemes.iter().map(|(_, g)| *g).collect();
+
+    let ops = myers::diff(&old_tokens, &new_tokens);
+    if !ops.iter().any(|op| op.is_equal()) {
+        return None;
+    }
+
+    let mut old_ranges = Vec::new();
+    let mut new_ranges = Vec::new();
+    for op in ops.iter().filter(|op| !op.is_equal()) {
+        if let Some(r) = byte_range(&old_graphemes, op.old_range()) {
+            old_ranges.push(r);
+        }
+        if let Some(r) = byte_range(&new_graphemes, op.new_range()) {
+            new_ranges.push(r);
+        }
+    }
+    Some((old_ranges, new_ranges))
+}
+
+/// Pairs up the old and new lines of a replacement, line by line
+pub fn paired_lines(op: &DiffOp) -> impl Iterator<Item = (usize, usize)> {
+    op.old_range().zip(op.new_range())
+}
+
+fn graphemes(line: &str) -> Vec<(usize, &str)> {
+    let content = line.strip_suffix('\n').unwrap_or(line);
+    content.grapheme_indices(true).collect()
+}
+
+/// Converts a range of graphemes to a range of bytes
+fn byte_range(graphemes: &[(usize, &str)], range: Range<usize>) -> Option<Range<usize>> {
+    if range.is_empty() {
+        return None;
+    }
+    let start = graphemes[range.start].0;
+    let (last_index, last) = graphemes[range.end - 1];
+    Some(start..last_index + last.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Pairs = Vec<(usize, usize)>;
+
+    /// The changed ranges as `(start, end)` pairs
+    fn changes(old: &str, new: &str) -> Option<(Pairs, Pairs)> {
+        let pairs = |ranges: Highlights| ranges.iter().map(|r| (r.start, r.end)).collect();
+        changed_ranges(old, new).map(|(o, n)| (pairs(o), pairs(n)))
+    }
+
+    #[test]
+    fn changed_characters_are_highlighted() {
+        assert_eq!(
+            changes("hello world\n", "hello wurld\n"),
+            Some((vec![(7, 8)], vec![(7, 8)]))
+        );
+        // An insertion has no range on the old side
+        assert_eq!(
+            changes("let x = 1;", "let mut x = 1;"),
+            Some((vec![], vec![(4, 8)]))
+        );
+    }
+
+    #[test]
+    fn graphemes_are_not_split() {
+        // The combining accent belongs to its `e`, the whole grapheme is highlighted
+        assert_eq!(
+            changes("cafe\u{301} noir", "cafe noir"),
+            Some((vec![(3, 6)], vec![(3, 4)]))
+        );
+    }
+
+    #[test]
+    fn unrelated_lines_are_not_highlighted() {
+        assert_eq!(changes("abc\n", "xyz\n"), None);
+    }
+}
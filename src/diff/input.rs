@@ -0,0 +1,203 @@
+use super::encoding::Encoding;
+use crate::count::count_lines;
+use crate::utils::is_binary_content;
+use memchr::memchr_iter;
+use memmap2::Mmap;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::hash::Hash;
+use std::io;
+use std::mem::size_of;
+use std::path::{Path, PathBuf};
+
+/// The memory needed for each line, its slice, its id and its share of the hash table
+/// and of the work space of the diff algorithm
+const BYTES_PER_LINE: u64 = (2 * size_of::<&str>() + size_of::<u32>() + 48) as u64;
+
+enum Content {
+    Mapped(Mmap),
+    Owned(Vec<u8>),
+}
+
+/// A file loaded for comparison, regular files are memory mapped
+pub struct Input {
+    path: PathBuf,
+    content: Content,
+    /// Detected once, it takes a scan of the whole content
+    encoding: Encoding,
+}
+
+impl Input {
+    pub fn from_path<P: AsRef<Path>>(path: P) -> io::Result<Input> {
+        let path = path.as_ref();
+        let file = File::open(path)?;
+        let metadata = file.metadata()?;
+        // Empty files can not be mapped, and pipes or devices can not be mapped at all
+        let content = if metadata.is_file() && metadata.len() > 0 {
+            // Safety: the map is read only, if another process changes the file while it
+            // is compared the diff may be inconsistent but memory stays valid.
+            Content::Mapped(unsafe { Mmap::map(&file)? })
+        } else {
+            Content::Owned(fs::read(path)?)
+        };
+        let bytes: &[u8] = match &content {
+            Content::Mapped(map) => map,
+            Content::Owned(bytes) => bytes,
+        };
+        let encoding = Encoding::detect(bytes);
+        Ok(Input {
+            path: PathBuf::from(path),
+            content,
+            encoding,
+        })
+    }
+
+    #[inline]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    #[inline]
+    pub fn bytes(&self) -> &[u8] {
+        match &self.content {
+            Content::Mapped(map) => map,
+            Content::Owned(bytes) => bytes,
+        }
+    }
+
+    #[inline]
+    pub fn encoding(&self) -> Encoding {
+        self.encoding
+    }
+
+    /// Returns the content decoded by its detected encoding
+    pub fn text(&self) -> Cow<'_, str> {
+        self.encoding().decode(self.bytes())
+    }
+
+    /// Returns `true` if the content looks binary like `PathExt::is_binary` checks, and is
+    /// not UTF-16 text
+    pub fn is_binary(&self) -> bool {
+        is_binary_content(self.bytes()) && !self.encoding().is_utf16()
+    }
+
+    /// Returns the number of bytes a line diff of this input needs. The content itself is
+    /// mapped and not counted, but a decoded copy of it is.
+    pub fn line_diff_memory(&self) -> u64 {
+        (count_lines(self.bytes(), b'\n') + 1) * BYTES_PER_LINE
+            + self.encoding.decoded_size(self.bytes())
+    }
+}
+
+/// Splits the text into lines, the line terminators are kept so that a missing newline
+/// at the end of file is also a difference.
+pub fn split_lines(text: &str) -> Vec<&str> {
+    let mut lines = Vec::with_capacity(count_lines(text.as_bytes(), b'\n') as usize + 1);
+    let mut start = 0;
+    for end in memchr_iter(b'\n', text.as_bytes()) {
+        lines.push(&text[start..=end]);
+        start = end + 1;
+    }
+    if start < text.len() {
+        lines.push(&text[start..]);
+    }
+    lines
+}
+
+/// Replaces each line by an integer id, equal lines of both sides get the same id, so
+/// the diff algorithms compare integers instead of text.
+pub fn line_ids<'a, T: Hash + Eq>(old: &'a [T], new: &'a [T]) -> (Vec<u32>, Vec<u32>) {
+    let mut ids: HashMap<&'a T, u32> = HashMap::with_capacity(old.len());
+    let mut intern = |lines: &'a [T]| -> Vec<u32> {
+        lines
+            .iter()
+            .map(|line| {
+                let next = ids.len() as u32;
+                *ids.entry(line).or_insert(next)
+            })
+            .collect()
+    };
+    let old_ids = intern(old);
+    let new_ids = intern(new);
+    (old_ids, new_ids)
+}
+
+/// Parses a size like `512M` or `2G`, the suffixes are powers of 1024
+pub fn parse_size(size: &str) -> Option<u64> {
+    let size = size.trim();
+    let (number, shift) = match size.char_indices().last()? {
+        (i, 'k') | (i, 'K') => (&size[..i], 10),
+        (i, 'm') | (i, 'M') => (&size[..i], 20),
+        (i, 'g') | (i, 'G') => (&size[..i], 30),
+        (i, 't') | (i, 'T') => (&size[..i], 40),
+        _ => (size, 0),
+    };
+    number.trim().parse::<u64>().ok()?.checked_mul(1 << shift)
+}
+
+/// Formats a number of bytes with a binary unit
+pub fn format_size(size: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut value = size as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit + 1 < UNITS.len() {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", size)
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn lines_keep_their_terminators() {
+        assert_eq!(split_lines("a\nb\r\nc"), ["a\n", "b\r\n", "c"]);
+        assert_eq!(split_lines("a\n"), ["a\n"]);
+        assert!(split_lines("").is_empty());
+    }
+
+    #[test]
+    fn equal_lines_share_an_id() {
+        let (old, new) = line_ids(&["a", "b", "a"], &["b", "c"]);
+        assert_eq!((old, new), (vec![0, 1, 0], vec![1, 2]));
+    }
+
+    #[test]
+    fn sizes_are_parsed_and_formatted() {
+        assert_eq!(parse_size("512"), Some(512));
+        assert_eq!(parse_size("4k"), Some(4096));
+        assert_eq!(parse_size("2G"), Some(2 << 30));
+        assert_eq!(parse_size("G"), None);
+        assert_eq!(parse_size("99999999999T"), None);
+        assert_eq!(format_size(1000), "1000 B");
+        assert_eq!(format_size(3 << 29), "1.5 GiB");
+    }
+
+    #[test]
+    fn files_are_mapped_and_empty_files_read() {
+        let dir = env::temp_dir().join(format!("show-input-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let (full, empty) = (dir.join("full"), dir.join("empty"));
+        fs::write(&full, "one\ntwo\n").unwrap();
+        fs::write(&empty, "").unwrap();
+
+        let input = Input::from_path(&full).unwrap();
+        assert!(matches!(input.content, Content::Mapped(_)));
+        assert_eq!(input.text(), "one\ntwo\n");
+        // Three lines at most, UTF-8 text is not copied
+        assert_eq!(input.line_diff_memory(), 3 * BYTES_PER_LINE);
+
+        let input = Input::from_path(&empty).unwrap();
+        assert!(matches!(input.content, Content::Owned(_)));
+        assert_eq!(input.bytes(), b"");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,362 @@
+//! Interactive review of the hunks of two files, `show diff --interactive`.
+//!
+//! Each hunk is shown like the unified output shows it. A hunk is accepted from the left
+//! file, from the right file, or edited in `$EDITOR`. The merged file takes the lines
+//! between hunks from the left file.
+
+use super::hunk::Hunk;
+use super::unified::UnifiedPrinter;
+use ansi_term::{Color::*, Style};
+use crossterm::cursor::{Hide, MoveTo, Show};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::terminal::{
+    self, disable_raw_mode, enable_raw_mode, Clear, ClearType, EnterAlternateScreen,
+    LeaveAlternateScreen,
+};
+use crossterm::{execute, queue};
+use std::env;
+use std::fs::{self, DirBuilder, OpenOptions};
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The editor used if neither `VISUAL` nor `EDITOR` is set
+const DEFAULT_EDITOR: &str = "vi";
+
+/// The keys, short enough for 80 columns
+const HELP: &str = "n/p next/previous  l left  r right  e edit  j/k scroll  w write  q quit";
+
+/// What is taken for a hunk into the merged file
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Decision {
+    /// Not decided yet, the left lines are kept
+    Undecided,
+    Left,
+    Right,
+    /// The edited lines replace the hunk
+    Edited(String),
+}
+
+/// Steps through the hunks and lets the user decide each of them
+pub struct Review<'a> {
+    old_name: &'a str,
+    new_name: &'a str,
+    old_lines: &'a [&'a str],
+    new_lines: &'a [&'a str],
+    hunks: &'a [Hunk],
+    decisions: Vec<Decision>,
+    current: usize,
+    scroll: usize,
+    /// An error which is shown instead of the help until the next key
+    message: Option<String>,
+}
+
+impl<'a> Review<'a> {
+    pub fn new(
+        old_name: &'a str,
+        new_name: &'a str,
+        old_lines: &'a [&'a str],
+        new_lines: &'a [&'a str],
+        hunks: &'a [Hunk],
+    ) -> Self {
+        Self {
+            old_name,
+            new_name,
+            old_lines,
+            new_lines,
+            hunks,
+            decisions: vec![Decision::Undecided; hunks.len()],
+            current: 0,
+            scroll: 0,
+            message: None,
+        }
+    }
+
+    /// Runs the review in the alternate screen. Returns the decisions, or `None` if the
+    /// user quit without writing.
+    pub fn run(mut self) -> io::Result<Option<Vec<Decision>>> {
+        let _screen = Screen::enter()?;
+        loop {
+            self.draw()?;
+            let key = match event::read()? {
+                Event::Key(key) if key.kind == KeyEventKind::Press => key,
+                _ => continue,
+            };
+            match key {
+                KeyEvent {
+                    code: KeyCode::Char('c'),
+                    modifiers: KeyModifiers::CONTROL,
+                    ..
+                }
+                | KeyEvent {
+                    code: KeyCode::Char('q'),
+                    ..
+                } => return Ok(None),
+                KeyEvent {
+                    code: KeyCode::Char('w'),
+                    ..
+                } => return Ok(Some(self.decisions)),
+                KeyEvent { code, .. } => {
+                    self.message = None;
+                    self.handle(code);
+                }
+            }
+        }
+    }
+
+    fn handle(&mut self, code: KeyCode) {
+        match code {
+            KeyCode::Char('n') | KeyCode::Right | KeyCode::Char(' ') => {
+                self.go_to(self.current + 1)
+            }
+            KeyCode::Char('p') | KeyCode::Left => self.go_to(self.current.saturating_sub(1)),
+            KeyCode::Char('j') | KeyCode::Down => self.scroll += 1,
+            KeyCode::Char('k') | KeyCode::Up => self.scroll = self.scroll.saturating_sub(1),
+            KeyCode::Char('l') => self.decide(Decision::Left),
+            KeyCode::Char('r') => self.decide(Decision::Right),
+            KeyCode::Char('e') => match self.edit() {
+                Ok(text) => self.decide(Decision::Edited(text)),
+                Err(e) => self.message = Some(format!("Edit failed: {}", e)),
+            },
+            _ => {}
+        }
+    }
+
+    fn go_to(&mut self, index: usize) {
+        self.current = index.min(self.hunks.len().saturating_sub(1));
+        self.scroll = 0;
+    }
+
+    /// Decides the current hunk and moves on to the next one
+    fn decide(&mut self, decision: Decision) {
+        self.decisions[self.current] = decision;
+        self.go_to(self.current + 1);
+    }
+
+    /// Opens the current lines of the hunk in the editor and returns the edited text
+    fn edit(&self) -> io::Result<String> {
+        let hunk = &self.hunks[self.current];
+        let text = match &self.decisions[self.current] {
+            Decision::Edited(text) => text.clone(),
+            Decision::Left => self.old_lines[hunk.old_range()].concat(),
+            Decision::Undecided | Decision::Right => self.new_lines[hunk.new_range()].concat(),
+        };
+        let dir = private_temp_dir()?;
+        let path = dir.join(format!("hunk-{}.txt", self.current + 1));
+        let written = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)
+            .and_then(|mut file| file.write_all(text.as_bytes()));
+        if let Err(e) = written {
+            let _ = fs::remove_dir_all(&dir);
+            return Err(e);
+        }
+
+        let editor = env::var("VISUAL")
+            .or_else(|_| env::var("EDITOR"))
+            .unwrap_or_else(|_| DEFAULT_EDITOR.to_string());
+        // The editor may be a command with arguments, like `code --wait`
+        let mut words = editor.split_whitespace();
+        let program = words.next().unwrap_or(DEFAULT_EDITOR);
+        let status = Screen::suspend(|| Command::new(program).args(words).arg(&path).status());
+        let edited = match status {
+            Ok(status) if status.success() => fs::read_to_string(&path),
+            Ok(status) => Err(io::Error::other(format!(
+                "{} exited with {}",
+                editor, status
+            ))),
+            Err(e) => Err(io::Error::new(e.kind(), format!("{}: {}", editor, e))),
+        };
+        let _ = fs::remove_dir_all(&dir);
+        edited
+    }
+
+    fn draw(&self) -> io::Result<()> {
+        let (width, height) = terminal::size()?;
+        let hunk = &self.hunks[self.current];
+        let mut body = Vec::new();
+        UnifiedPrinter::new(true).write_hunk(&mut body, hunk, self.old_lines, self.new_lines)?;
+        let body = String::from_utf8_lossy(&body);
+        let lines: Vec<&str> = body.lines().collect();
+
+        let decided = self
+            .decisions
+            .iter()
+            .filter(|d| **d != Decision::Undecided)
+            .count();
+        let title = format!(
+            "{} -> {}  hunk {}/{}  {}  ({} of {} decided)",
+            self.old_name,
+            self.new_name,
+            self.current + 1,
+            self.hunks.len(),
+            describe(&self.decisions[self.current]),
+            decided,
+            self.hunks.len()
+        );
+
+        let mut out = io::stdout().lock();
+        queue!(out, Clear(ClearType::All), MoveTo(0, 0))?;
+        write!(out, "{}\r\n", Style::new().bold().paint(title))?;
+        // The title and the help take a row each
+        let rows = (height as usize).saturating_sub(2);
+        let scroll = self.scroll.min(lines.len().saturating_sub(rows));
+        for line in lines.iter().skip(scroll).take(rows) {
+            write!(out, "{}\r\n", line)?;
+        }
+        let (style, help) = match &self.message {
+            Some(message) => (Red.normal(), message.as_str()),
+            None => (Style::new().dimmed(), HELP),
+        };
+        let help: String = help.chars().take(width as usize).collect();
+        queue!(out, MoveTo(0, height.saturating_sub(1)))?;
+        write!(out, "{}", style.paint(help))?;
+        out.flush()
+    }
+}
+
+fn describe(decision: &Decision) -> String {
+    match decision {
+        Decision::Undecided => Yellow.paint("undecided").to_string(),
+        Decision::Left => Red.paint("accept left").to_string(),
+        Decision::Right => Green.paint("accept right").to_string(),
+        Decision::Edited(_) => Cyan.paint("edited").to_string(),
+    }
+}
+
+/// Creates a new directory in the temporary directory which only the user can access,
+/// so that a file in it can not be replaced or read by others. A name which is taken is
+/// not reused, even if it is a link to a directory.
+fn private_temp_dir() -> io::Result<PathBuf> {
+    let mut builder = DirBuilder::new();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::DirBuilderExt;
+        builder.mode(0o700);
+    }
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.subsec_nanos());
+    let mut attempt = 0;
+    loop {
+        let dir = env::temp_dir().join(format!(
+            "show-diff-{}-{}-{}",
+            std::process::id(),
+            nanos,
+            attempt
+        ));
+        match builder.create(&dir) {
+            Ok(()) => return Ok(dir),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists && attempt < 100 => attempt += 1,
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Builds the merged file, undecided hunks keep the left lines
+pub fn merge(
+    old_lines: &[&str],
+    new_lines: &[&str],
+    hunks: &[Hunk],
+    decisions: &[Decision],
+) -> String {
+    let mut merged = String::new();
+    let mut next = 0;
+    for (hunk, decision) in hunks.iter().zip(decisions) {
+        let (old, new) = (hunk.old_range(), hunk.new_range());
+        merged.extend(old_lines[next..old.start].iter().copied());
+        match decision {
+            Decision::Undecided | Decision::Left => {
+                merged.extend(old_lines[old.clone()].iter().copied())
+            }
+            Decision::Right => merged.extend(new_lines[new].iter().copied()),
+            Decision::Edited(text) => merged.push_str(text),
+        }
+        next = old.end;
+    }
+    merged.extend(old_lines[next..].iter().copied());
+    merged
+}
+
+/// The terminal in raw mode and the alternate screen, restored when it is dropped
+struct Screen;
+
+impl Screen {
+    fn enter() -> io::Result<Self> {
+        enable_raw_mode()?;
+        execute!(io::stdout(), EnterAlternateScreen, Hide)?;
+        Ok(Screen)
+    }
+
+    /// Restores the terminal while `f` runs, like for an editor
+    fn suspend<T>(f: impl FnOnce() -> T) -> T {
+        let _ = execute!(io::stdout(), Show, LeaveAlternateScreen);
+        let _ = disable_raw_mode();
+        let result = f();
+        let _ = enable_raw_mode();
+        let _ = execute!(io::stdout(), EnterAlternateScreen, Hide);
+        result
+    }
+}
+
+impl Drop for Screen {
+    fn drop(&mut self) {
+        let _ = execute!(io::stdout(), Show, LeaveAlternateScreen);
+        let _ = disable_raw_mode();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diff::hunk::group_hunks;
+    use crate::diff::myers;
+
+    const OLD: &[&str] = &["a\n", "b\n", "c\n", "d\n", "e\n", "f\n"];
+    const NEW: &[&str] = &["A\n", "b\n", "c\n", "d\n", "e\n", "F\n"];
+
+    #[test]
+    fn decisions_are_merged() {
+        let hunks = group_hunks(&myers::diff(OLD, NEW), 1);
+        assert_eq!(hunks.len(), 2);
+        let merge_with = |decisions: &[Decision]| merge(OLD, NEW, &hunks, decisions);
+        assert_eq!(
+            merge_with(&[Decision::Undecided, Decision::Right]),
+            "a\nb\nc\nd\ne\nF\n"
+        );
+        assert_eq!(
+            merge_with(&[Decision::Edited("x\ny\n".to_string()), Decision::Left]),
+            "x\ny\nc\nd\ne\nf\n"
+        );
+    }
+
+    #[test]
+    fn keys_decide_and_move() {
+        let hunks = group_hunks(&myers::diff(OLD, NEW), 1);
+        let mut review = Review::new("old", "new", OLD, NEW, &hunks);
+        review.handle(KeyCode::Char('r'));
+        assert_eq!(review.current, 1);
+        review.handle(KeyCode::Char('j'));
+        review.handle(KeyCode::Char('l'));
+        // The last hunk stays current and the scroll is reset
+        assert_eq!((review.current, review.scroll), (1, 0));
+        review.handle(KeyCode::Char('p'));
+        review.handle(KeyCode::Char('p'));
+        assert_eq!(review.current, 0);
+        assert_eq!(review.decisions, [Decision::Right, Decision::Left]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn temp_dir_is_private() {
+        use std::os::unix::fs::PermissionsExt;
+        let (first, second) = (private_temp_dir().unwrap(), private_temp_dir().unwrap());
+        assert_ne!(first, second);
+        let mode = fs::metadata(&first).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o700);
+        fs::remove_dir(&first).unwrap();
+        fs::remove_dir(&second).unwrap();
+    }
+}
@@ -0,0 +1,335 @@
+//! JSON output of `show diff --format json`.
+//!
+//! The output is a single document, built from the edit script of each file:
+//!
+//! ```text
+//! {
+//!   "version": 1,
+//!   "files": [
+//!     {
+//!       "old_path": "a.txt",        // null if the file was added
+//!       "new_path": "b.txt",        // null if the file was deleted
+//!       "status": "modified",       // "modified", "added" or "deleted"
+//!       "binary": false,            // binary files have no hunks
+//!       "encoding": { "old": "UTF-16LE", "new": "UTF-8" },  // only if it changed
+//!       "line_endings": { "old": "CRLF", "new": "LF" },     // only if they changed
+//!       "hunks": [
+//!         {
+//!           "old_start": 1, "old_count": 3,
+//!           "new_start": 1, "new_count": 4,
+//!           "ops": [
+//!             { "op": "equal",   "old_start": 1, "new_start": 1, "lines": ["a\n"] },
+//!             { "op": "delete",  "old_start": 2, "new_start": 2, "old_lines": ["b\n"] },
+//!             { "op": "insert",  "old_start": 3, "new_start": 2, "new_lines": ["x\n"] },
+//!             { "op": "replace", "old_start": 3, "new_start": 3,
+//!               "old_lines": ["c\n"], "new_lines": ["y\n", "z\n"] }
+//!           ]
+//!         }
+//!       ],
+//!       "moved": [
+//!         { "old_start": 10, "old_count": 5, "new_start": 40, "new_count": 5 }
+//!       ]
+//!     }
+//!   ]
+//! }
+//! ```
+//!
+//! Line numbers are 1-based. The start of an empty range is the number of the line which
+//! follows it, so an insertion at the top of a file has `old_start` 1. Lines keep their
+//! terminators, a missing newline at the end of file is visible as a line without `\n`.
+//! Text is decoded from UTF-8, UTF-16 or Latin-1 and lines are compared without carriage
+//! returns if the line endings changed. Only files which differ are listed, in path order.
+//!
+//! The `version` is increased whenever a field is removed or changes its meaning, new
+//! fields may be added within the same version.
+
+use super::encoding::Findings;
+use super::hunk::Hunk;
+use super::moved::MovedBlock;
+use super::ops::DiffOp;
+use serde::Serialize;
+use std::io::{self, Write};
+
+/// The version of the document schema
+pub const SCHEMA_VERSION: u32 = 1;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Status {
+    Modified,
+    Added,
+    Deleted,
+}
+
+/// The differences of one pair of files
+#[derive(Clone, Debug, Serialize)]
+pub struct FileDiff<'a> {
+    old_path: Option<String>,
+    new_path: Option<String>,
+    status: Status,
+    binary: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    encoding: Option<JsonChange>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    line_endings: Option<JsonChange>,
+    hunks: Vec<JsonHunk<'a>>,
+    moved: Vec<JsonMove>,
+}
+
+/// A property of the file which changed, like its encoding
+#[derive(Clone, Debug, Serialize)]
+struct JsonChange {
+    old: String,
+    new: String,
+}
+
+#[derive(Clone, Debug, Serialize)]
+struct JsonHunk<'a> {
+    old_start: usize,
+    old_count: usize,
+    new_start: usize,
+    new_count: usize,
+    ops: Vec<JsonOp<'a>>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+enum JsonOp<'a> {
+    Equal {
+        old_start: usize,
+        new_start: usize,
+        lines: &'a [&'a str],
+    },
+    Delete {
+        old_start: usize,
+        new_start: usize,
+        old_lines: &'a [&'a str],
+    },
+    Insert {
+        old_start: usize,
+        new_start: usize,
+        new_lines: &'a [&'a str],
+    },
+    Replace {
+        old_start: usize,
+        new_start: usize,
+        old_lines: &'a [&'a str],
+        new_lines: &'a [&'a str],
+    },
+}
+
+#[derive(Clone, Debug, Serialize)]
+struct JsonMove {
+    old_start: usize,
+    old_count: usize,
+    new_start: usize,
+    new_count: usize,
+}
+
+impl<'a> FileDiff<'a> {
+    /// Creates the entry of a text file, the ops index `old_lines` and `new_lines`
+    pub fn new(
+        old_path: Option<String>,
+        new_path: Option<String>,
+        hunks: &[Hunk],
+        old_lines: &'a [&'a str],
+        new_lines: &'a [&'a str],
+        moved: &[MovedBlock],
+    ) -> Self {
+        let hunks = hunks
+            .iter()
+            .map(|hunk| {
+                let (old, new) = (hunk.old_range(), hunk.new_range());
+                JsonHunk {
+                    old_start: old.start + 1,
+                    old_count: old.len(),
+                    new_start: new.start + 1,
+                    new_count: new.len(),
+                    ops: hunk
+                        .ops()
+                        .iter()
+                        .map(|op| json_op(op, old_lines, new_lines))
+                        .collect(),
+                }
+            })
+            .collect();
+        let moved = moved
+            .iter()
+            .map(|block| JsonMove {
+                old_start: block.old.start + 1,
+                old_count: block.old.len(),
+                new_start: block.new.start + 1,
+                new_count: block.new.len(),
+            })
+            .collect();
+        Self {
+            status: status(&old_path, &new_path),
+            old_path,
+            new_path,
+            binary: false,
+            encoding: None,
+            line_endings: None,
+            hunks,
+            moved,
+        }
+    }
+
+    /// Adds the encoding and line ending changes
+    pub fn with_findings(mut self, findings: &Findings) -> Self {
+        let change = |old: &dyn ToString, new: &dyn ToString| JsonChange {
+            old: old.to_string(),
+            new: new.to_string(),
+        };
+        self.encoding = findings.encoding.map(|(old, new)| change(&old, &new));
+        self.line_endings = findings.line_ending.map(|(old, new)| change(&old, &new));
+        self
+    }
+
+    /// Creates the entry of a binary file, which has no hunks
+    pub fn binary(old_path: Option<String>, new_path: Option<String>) -> Self {
+        Self {
+            status: status(&old_path, &new_path),
+            old_path,
+            new_path,
+            binary: true,
+            encoding: None,
+            line_endings: None,
+            hunks: vec![],
+            moved: vec![],
+        }
+    }
+}
+
+fn status(old_path: &Option<String>, new_path: &Option<String>) -> Status {
+    match (old_path, new_path) {
+        (None, Some(_)) => Status::Added,
+        (Some(_), None) => Status::Deleted,
+        _ => Status::Modified,
+    }
+}
+
+fn json_op<'a>(op: &DiffOp, old_lines: &'a [&'a str], new_lines: &'a [&'a str]) -> JsonOp<'a> {
+    let (old, new) = (op.old_range(), op.new_range());
+    let (old_start, new_start) = (old.start + 1, new.start + 1);
+    match op {
+        DiffOp::Equal { .. } => JsonOp::Equal {
+            old_start,
+            new_start,
+            lines: &old_lines[old],
+        },
+        DiffOp::Delete { .. } => JsonOp::Delete {
+            old_start,
+            new_start,
+            old_lines: &old_lines[old],
+        },
+        DiffOp::Insert { .. } => JsonOp::Insert {
+            old_start,
+            new_start,
+            new_lines: &new_lines[new],
+        },
+        DiffOp::Replace { .. } => JsonOp::Replace {
+            old_start,
+            new_start,
+            old_lines: &old_lines[old],
+            new_lines: &new_lines[new],
+        },
+    }
+}
+
+/// Writes the document file by file, so only one pair of files is loaded at a time
+pub struct JsonWriter<W: Write> {
+    out: W,
+    files: usize,
+}
+
+impl<W: Write> JsonWriter<W> {
+    pub fn new(mut out: W) -> io::Result<Self> {
+        write!(out, "{{\"version\":{},\"files\":[", SCHEMA_VERSION)?;
+        Ok(Self { out, files: 0 })
+    }
+
+    pub fn write_file(&mut self, file: &FileDiff) -> io::Result<()> {
+        if self.files > 0 {
+            self.out.write_all(b",")?;
+        }
+        serde_json::to_writer(&mut self.out, file)?;
+        self.files += 1;
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> io::Result<()> {
+        self.out.write_all(b"]}\n")?;
+        self.out.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diff::encoding::{Encoding, LineEnding};
+    use crate::diff::hunk::group_hunks;
+    use crate::diff::myers;
+
+    fn document(files: &[FileDiff]) -> String {
+        let mut out = Vec::new();
+        let mut writer = JsonWriter::new(&mut out).unwrap();
+        for file in files {
+            writer.write_file(file).unwrap();
+        }
+        writer.finish().unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn files_are_written_in_one_document() {
+        let (old, new) = (["a\n", "b\n"], ["a\n", "c\n"]);
+        let hunks = group_hunks(&myers::diff(&old, &new), 3);
+        let path = || Some("a.txt".to_string());
+        let text = FileDiff::new(path(), path(), &hunks, &old, &new, &[]);
+        let binary = FileDiff::binary(None, Some("logo.png".to_string()));
+        assert_eq!(
+            document(&[text, binary]),
+            concat!(
+                r#"{"version":1,"files":["#,
+                r#"{"old_path":"a.txt","new_path":"a.txt","status":"modified","binary":false,"#,
+                r#""hunks":[{"old_start":1,"old_count":2,"new_start":1,"new_count":2,"ops":["#,
+                r#"{"op":"equal","old_start":1,"new_start":1,"lines":["a\n"]},"#,
+                r#"{"op":"replace","old_start":2,"new_start":2,"#,
+                r#""old_lines":["b\n"],"new_lines":["c\n"]}]}],"moved":[]},"#,
+                r#"{"old_path":null,"new_path":"logo.png","status":"added","binary":true,"#,
+                r#""hunks":[],"moved":[]}]}"#,
+                "\n"
+            )
+        );
+    }
+
+    #[test]
+    fn insertion_at_the_top_starts_at_line_one() {
+        let (old, new) = (["a\n"], ["x\n", "a\n"]);
+        let hunks = group_hunks(&myers::diff(&old, &new), 0);
+        let moved = [MovedBlock {
+            old: 0..1,
+            new: 1..2,
+        }];
+        let file = FileDiff::new(None, Some("b".to_string()), &hunks, &old, &new, &moved);
+        let json = serde_json::to_string(&file).unwrap();
+        assert!(json.contains(r#""status":"added""#));
+        assert!(json.contains(r#"{"op":"insert","old_start":1,"new_start":1,"new_lines":["x\n"]}"#));
+        assert!(
+            json.contains(r#""moved":[{"old_start":1,"old_count":1,"new_start":2,"new_count":1}]"#)
+        );
+    }
+
+    #[test]
+    fn findings_are_added_when_they_changed() {
+        let findings = Findings {
+            encoding: Some((Encoding::Utf16LeBom, Encoding::Utf8)),
+            line_ending: Some((LineEnding::CrLf, LineEnding::Lf)),
+        };
+        let file = FileDiff::binary(Some("a".to_string()), None).with_findings(&findings);
+        let json = serde_json::to_string(&file).unwrap();
+        assert!(json.contains(r#""status":"deleted""#));
+        assert!(json.contains(r#""encoding":{"old":"UTF-16LE with BOM","new":"UTF-8"}"#));
+        assert!(json.contains(r#""line_endings":{"old":"CRLF","new":"LF"}"#));
+    }
+}
@@ -0,0 +1,224 @@
+//! Moved block detection, runs of deleted lines which are added elsewhere.
+//!
+//! Lines are compared without white space, so re-indented blocks are found too. Like git,
+//! a block needs at least 20 alphanumeric characters, otherwise every moved `}` would
+//! count.
+
+use super::ops::DiffOp;
+use std::collections::HashMap;
+use std::ops::Range;
+
+/// The number of alphanumeric characters a block needs to count as moved
+const MIN_ALNUM_CHARS: usize = 20;
+
+/// The number of places a line may have been moved from which are tried
+const MAX_CANDIDATES: usize = 128;
+
+/// A block of deleted lines which is added at another place
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MovedBlock {
+    pub old: Range<usize>,
+    pub new: Range<usize>,
+}
+
+/// The lines which belong to moved blocks
+#[derive(Clone, Debug, Default)]
+pub struct MovedLines {
+    old: Vec<bool>,
+    new: Vec<bool>,
+}
+
+impl MovedLines {
+    pub fn new(blocks: &[MovedBlock], old_len: usize, new_len: usize) -> Self {
+        let mut moved = Self {
+            old: vec![false; old_len],
+            new: vec![false; new_len],
+        };
+        for block in blocks {
+            moved.old[block.old.clone()]
+                .iter_mut()
+                .for_each(|m| *m = true);
+            moved.new[block.new.clone()]
+                .iter_mut()
+                .for_each(|m| *m = true);
+        }
+        moved
+    }
+
+    #[inline]
+    pub fn is_old_moved(&self, index: usize) -> bool {
+        self.old.get(index).copied().unwrap_or(false)
+    }
+
+    #[inline]
+    pub fn is_new_moved(&self, index: usize) -> bool {
+        self.new.get(index).copied().unwrap_or(false)
+    }
+}
+
+/// Returns the blocks of deleted lines which are added elsewhere, in the order of the
+/// new file. Each line belongs to one block at most.
+pub fn find_moved_blocks(
+    ops: &[DiffOp],
+    old_lines: &[&str],
+    new_lines: &[&str],
+) -> Vec<MovedBlock> {
+    // Only changed lines get a key, equal lines never take part in a move
+    let mut ids: HashMap<String, u32> = HashMap::new();
+    let mut old_keys = vec![None; old_lines.len()];
+    let mut new_keys = vec![None; new_lines.len()];
+    // The change and the position in it of each changed line
+    let mut old_places = vec![None; old_lines.len()];
+    let mut new_places = vec![None; new_lines.len()];
+    for (n, op) in ops.iter().enumerate().filter(|(_, op)| !op.is_equal()) {
+        for (k, i) in op.old_range().enumerate() {
+            old_keys[i] = Some(key_id(&mut ids, old_lines[i]));
+            old_places[i] = Some((n, k));
+        }
+        for (k, j) in op.new_range().enumerate() {
+            new_keys[j] = Some(key_id(&mut ids, new_lines[j]));
+            new_places[j] = Some((n, k));
+        }
+    }
+    // A line replaced by itself in other white space is changed in place, not moved
+    let in_place = |i: usize, j: usize| old_places[i].is_some() && old_places[i] == new_places[j];
+
+    let mut sources: HashMap<u32, Vec<usize>> = HashMap::new();
+    for (i, key) in old_keys.iter().enumerate() {
+        if let Some(key) = key {
+            sources.entry(*key).or_default().push(i);
+        }
+    }
+
+    let mut old_moved = vec![false; old_lines.len()];
+    let mut blocks = Vec::new();
+    let mut j = 0;
+    while j < new_lines.len() {
+        let candidates = match new_keys[j].and_then(|key| sources.get(&key)) {
+            Some(candidates) => candidates,
+            None => {
+                j += 1;
+                continue;
+            }
+        };
+
+        let run_len = |i: usize| {
+            (0..)
+                .take_while(|&k| {
+                    i + k < old_lines.len()
+                        && j + k < new_lines.len()
+                        && !old_moved[i + k]
+                        && old_keys[i + k].is_some()
+                        && old_keys[i + k] == new_keys[j + k]
+                        && !in_place(i + k, j + k)
+                })
+                .count()
+        };
+        let best = candidates
+            .iter()
+            .filter(|&&i| !old_moved[i])
+            .take(MAX_CANDIDATES)
+            .map(|&i| (run_len(i), i))
+            .max_by_key(|&(len, i)| (len, std::cmp::Reverse(i)));
+
+        let (len, i) = best.unwrap_or((0, 0));
+        match len {
+            0 => j += 1,
+            _ if alnum_chars(&new_lines[j..j + len]) < MIN_ALNUM_CHARS => j += 1,
+            _ => {
+                old_moved[i..i + len].iter_mut().for_each(|m| *m = true);
+                blocks.push(MovedBlock {
+                    old: i..i + len,
+                    new: j..j + len,
+                });
+                j += len;
+            }
+        }
+    }
+    blocks
+}
+
+fn key_id(ids: &mut HashMap<String, u32>, line: &str) -> u32 {
+    let key: String = line.chars().filter(|c| !c.is_whitespace()).collect();
+    let next = ids.len() as u32;
+    *ids.entry(key).or_insert(next)
+}
+
+fn alnum_chars(lines: &[&str]) -> usize {
+    lines
+        .iter()
+        .map(|line| line.chars().filter(|c| c.is_alphanumeric()).count())
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diff::myers;
+
+    fn moved(old: &[&str], new: &[&str]) -> Vec<MovedBlock> {
+        find_moved_blocks(&myers::diff(old, new), old, new)
+    }
+
+    #[test]
+    fn moved_block_is_found() {
+        let old = [
+            "fn first() {",
+            "    call_something();",
+            "}",
+            "a",
+            "b",
+            "c",
+            "d",
+        ];
+        let new = [
+            "a",
+            "b",
+            "c",
+            "d",
+            "fn first() {",
+            "    call_something();",
+            "}",
+        ];
+        assert_eq!(
+            moved(&old, &new),
+            [MovedBlock {
+                old: 0..3,
+                new: 4..7
+            }]
+        );
+    }
+
+    #[test]
+    fn reindented_block_is_moved() {
+        let old = ["fn first() {", "    call_something();", "}", "a", "b"];
+        let new = ["a", "b", "  fn first() {", "      call_something();", "  }"];
+        assert_eq!(moved(&old, &new).len(), 1);
+    }
+
+    #[test]
+    fn reindented_in_place_is_not_moved() {
+        let old = ["a", "    call_something_else();", "b"];
+        let new = ["a", "        call_something_else();", "b"];
+        assert!(moved(&old, &new).is_empty());
+    }
+
+    #[test]
+    fn short_blocks_are_not_moved() {
+        let old = ["}", "x", "a", "b"];
+        let new = ["a", "b", "}", "x"];
+        assert!(moved(&old, &new).is_empty());
+    }
+
+    #[test]
+    fn lines_are_marked() {
+        let blocks = [MovedBlock {
+            old: 1..2,
+            new: 0..1,
+        }];
+        let lines = MovedLines::new(&blocks, 3, 2);
+        assert!(lines.is_old_moved(1) && lines.is_new_moved(0));
+        assert!(!lines.is_old_moved(0) && !lines.is_new_moved(1));
+        assert!(!lines.is_old_moved(5));
+    }
+}
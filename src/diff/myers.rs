@@ -0,0 +1,291 @@
+//! Linear space variant of the Myers diff algorithm.
+//!
+//! See "An O(ND) Difference Algorithm and Its Variations" by Eugene W. Myers,
+//! the middle snake is searched from both ends and the problem is divided at it.
+
+use super::ops::{common_prefix_len, common_suffix_len, Capture, DiffOp};
+use std::ops::{Index, IndexMut};
+
+/// The furthest reaching x coordinates, indexed by diagonal `k` which may be negative.
+struct V {
+    offset: isize,
+    v: Vec<usize>,
+}
+
+impl V {
+    fn new(max_d: usize) -> Self {
+        Self {
+            offset: max_d as isize,
+            v: vec![0; 2 * max_d + 1],
+        }
+    }
+}
+
+impl Index<isize> for V {
+    type Output = usize;
+
+    #[inline]
+    fn index(&self, k: isize) -> &usize {
+        &self.v[(k + self.offset) as usize]
+    }
+}
+
+impl IndexMut<isize> for V {
+    #[inline]
+    fn index_mut(&mut self, k: isize) -> &mut usize {
+        &mut self.v[(k + self.offset) as usize]
+    }
+}
+
+#[inline]
+fn max_d(old_len: usize, new_len: usize) -> usize {
+    (old_len + new_len).div_ceil(2) + 1
+}
+
+/// Returns the edit script which turns `old` into `new`.
+///
+/// The search for the shortest edit script gives up on very expensive inputs and takes
+/// a good split instead.
+pub fn diff<T: PartialEq>(old: &[T], new: &[T]) -> Vec<DiffOp> {
+    let mut capture = Capture::new();
+    diff_into(old, 0, new, 0, false, &mut capture);
+    capture.into_ops()
+}
+
+/// Compares `old` and `new`, which start at the given indices of the whole sequences,
+/// and emits the operations to `capture`.
+pub fn diff_into<T: PartialEq>(
+    old: &[T],
+    old_start: usize,
+    new: &[T],
+    new_start: usize,
+    minimal: bool,
+    capture: &mut Capture,
+) {
+    let max_d = max_d(old.len(), new.len());
+    let mut search = Search {
+        vf: V::new(max_d),
+        vb: V::new(max_d),
+        max_cost: if minimal {
+            usize::MAX
+        } else {
+            max_cost(old.len(), new.len())
+        },
+    };
+    search.conquer(old, old_start, new, new_start, capture);
+}
+
+/// The cost after which the heuristic stops searching for the middle snake,
+/// similar to what git does.
+fn max_cost(old_len: usize, new_len: usize) -> usize {
+    let mut cost = 1;
+    let mut n = old_len + new_len;
+    while n > 0 {
+        cost <<= 1;
+        n >>= 2;
+    }
+    cost.max(256)
+}
+
+struct Search {
+    vf: V,
+    vb: V,
+    max_cost: usize,
+}
+
+impl Search {
+    /// Finds the middle snake of `old` and `new` and returns the point where the problem can
+    /// be split into two smaller ones. Both sequences must be non-empty.
+    fn find_middle_snake<T: PartialEq>(&mut self, old: &[T], new: &[T]) -> Option<(usize, usize)> {
+        let Search { vf, vb, .. } = self;
+        let n = old.len();
+        let m = new.len();
+        let delta = n as isize - m as isize;
+        let odd = delta & 1 == 1;
+
+        vf[1] = 0;
+        vb[1] = 0;
+
+        for d in 0..max_d(n, m) as isize {
+            if d as usize > self.max_cost {
+                return furthest_point(vf, d - 1, n, m);
+            }
+
+            // Forward path
+            for k in (-d..=d).rev().step_by(2) {
+                let mut x = if k == -d || (k != d && vf[k - 1] < vf[k + 1]) {
+                    vf[k + 1]
+                } else {
+                    vf[k - 1] + 1
+                };
+                let y = (x as isize - k) as usize;
+                let (x0, y0) = (x, y);
+                if x < n && y < m {
+                    x += common_prefix_len(&old[x..], &new[y..]);
+                }
+                vf[k] = x;
+                if odd && (k - delta).abs() < d && vf[k] + vb[delta - k] >= n {
+                    return Some((x0, y0));
+                }
+            }
+
+            // Backward path
+            for k in (-d..=d).rev().step_by(2) {
+                let mut x = if k == -d || (k != d && vb[k - 1] < vb[k + 1]) {
+                    vb[k + 1]
+                } else {
+                    vb[k - 1] + 1
+                };
+                let mut y = (x as isize - k) as usize;
+                if x < n && y < m {
+                    let advance = common_suffix_len(&old[..n - x], &new[..m - y]);
+                    x += advance;
+                    y += advance;
+                }
+                vb[k] = x;
+                if !odd && (k - delta).abs() <= d && vb[k] + vf[delta - k] >= n {
+                    return Some((n - x, m - y));
+                }
+            }
+        }
+
+        None
+    }
+
+    fn conquer<T: PartialEq>(
+        &mut self,
+        mut old: &[T],
+        mut old_start: usize,
+        mut new: &[T],
+        mut new_start: usize,
+        capture: &mut Capture,
+    ) {
+        // Strip the common prefix
+        let prefix = common_prefix_len(old, new);
+        capture.equal(old_start, new_start, prefix);
+        old = &old[prefix..];
+        new = &new[prefix..];
+        old_start += prefix;
+        new_start += prefix;
+
+        // Strip the common suffix, it is emitted after the middle part
+        let suffix = common_suffix_len(old, new);
+        old = &old[..old.len() - suffix];
+        new = &new[..new.len() - suffix];
+
+        if old.is_empty() {
+            capture.insert(old_start, new_start, new.len());
+        } else if new.is_empty() {
+            capture.delete(old_start, old.len(), new_start);
+        } else if let Some((x, y)) = self.find_middle_snake(old, new) {
+            self.conquer(&old[..x], old_start, &new[..y], new_start, capture);
+            self.conquer(&old[x..], old_start + x, &new[y..], new_start + y, capture);
+        } else {
+            capture.delete(old_start, old.len(), new_start);
+            capture.insert(old_start + old.len(), new_start, new.len());
+        }
+
+        capture.equal(old_start + old.len(), new_start + new.len(), suffix);
+    }
+}
+
+/// Returns the point of the forward search which is the furthest away from the start,
+/// it is used as split point when the search becomes too expensive.
+fn furthest_point(vf: &V, d: isize, n: usize, m: usize) -> Option<(usize, usize)> {
+    (-d..=d)
+        .step_by(2)
+        .map(|k| (vf[k], (vf[k] as isize - k) as usize))
+        .filter(|&(x, y)| x <= n && y <= m && (x, y) != (0, 0) && (x, y) != (n, m))
+        .max_by_key(|&(x, y)| x + y)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diff::ops::apply;
+
+    const CASES: &[(&str, &str)] = &[
+        ("", ""),
+        ("", "abc"),
+        ("abc", ""),
+        ("abc", "abc"),
+        ("abcabba", "cbabac"),
+        ("abcdef", "azced"),
+        ("xaxbx", "abx"),
+        ("the quick brown fox", "a quick brown cat"),
+    ];
+
+    /// The number of deleted and inserted items of the script
+    fn cost(ops: &[DiffOp]) -> usize {
+        ops.iter()
+            .filter(|op| !op.is_equal())
+            .map(|op| op.old_range().len() + op.new_range().len())
+            .sum()
+    }
+
+    /// A reproducible sequence over a small alphabet
+    fn sequence(len: usize, seed: u32) -> Vec<u8> {
+        let mut state = seed;
+        (0..len)
+            .map(|_| {
+                state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+                b'a' + (state >> 16) as u8 % 4
+            })
+            .collect()
+    }
+
+    fn minimal(old: &[u8], new: &[u8]) -> Vec<DiffOp> {
+        let mut capture = Capture::new();
+        diff_into(old, 0, new, 0, true, &mut capture);
+        capture.into_ops()
+    }
+
+    #[test]
+    fn script_turns_old_into_new() {
+        for (old, new) in CASES {
+            let (old, new) = (old.as_bytes(), new.as_bytes());
+            assert_eq!(apply(&diff(old, new), old, new), new);
+            assert_eq!(apply(&minimal(old, new), old, new), new);
+        }
+    }
+
+    #[test]
+    fn minimal_script_is_shortest() {
+        // The example of the paper, which needs 5 edits
+        assert_eq!(cost(&minimal(b"abcabba", b"cbabac")), 5);
+        assert_eq!(cost(&minimal(b"abc", b"xyz")), 6);
+    }
+
+    #[test]
+    fn cost_heuristic_gives_a_valid_script() {
+        let (old, new) = (sequence(3000, 1), sequence(3000, 2));
+        assert!(max_cost(old.len(), new.len()) < old.len());
+        let fast = diff(&old, &new);
+        let shortest = minimal(&old, &new);
+        assert_eq!(apply(&fast, &old, &new), new);
+        assert_eq!(apply(&shortest, &old, &new), new);
+        // The search gave up and took a split which is not on the shortest path
+        assert!(cost(&fast) > cost(&shortest));
+    }
+
+    #[test]
+    fn start_indices_are_kept() {
+        let mut capture = Capture::new();
+        diff_into(b"ab", 10, b"b", 20, false, &mut capture);
+        assert_eq!(
+            capture.into_ops(),
+            vec![
+                DiffOp::Delete {
+                    old_index: 10,
+                    old_len: 1,
+                    new_index: 20
+                },
+                DiffOp::Equal {
+                    old_index: 11,
+                    new_index: 20,
+                    len: 1
+                }
+            ]
+        );
+    }
+}
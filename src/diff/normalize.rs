@@ -0,0 +1,184 @@
+use super::hunk::Hunk;
+use regex::Regex;
+use std::borrow::Cow;
+
+/// Decides which differences between lines are ignored.
+///
+/// Lines are matched by their keys, the printed lines are still the original ones.
+#[derive(Clone, Debug, Default)]
+pub struct Normalizer {
+    ignore_all_space: bool,
+    ignore_space_change: bool,
+    ignore_case: bool,
+    strip_trailing_cr: bool,
+    ignore_blank_lines: bool,
+    ignore_matching: Vec<Regex>,
+}
+
+impl Normalizer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ignores all white space within lines
+    pub fn ignore_all_space(mut self, yes: bool) -> Self {
+        self.ignore_all_space = yes;
+        self
+    }
+
+    /// Ignores changes in the amount of white space and white space at line end
+    pub fn ignore_space_change(mut self, yes: bool) -> Self {
+        self.ignore_space_change = yes;
+        self
+    }
+
+    pub fn ignore_case(mut self, yes: bool) -> Self {
+        self.ignore_case = yes;
+        self
+    }
+
+    /// Treats `\r\n` line endings like `\n`
+    pub fn strip_trailing_cr(mut self, yes: bool) -> Self {
+        self.strip_trailing_cr = yes;
+        self
+    }
+
+    /// Ignores changes whose lines are all blank
+    pub fn ignore_blank_lines(mut self, yes: bool) -> Self {
+        self.ignore_blank_lines = yes;
+        self
+    }
+
+    /// Ignores changes whose lines all match one of the patterns
+    pub fn ignore_matching(mut self, patterns: Vec<Regex>) -> Self {
+        self.ignore_matching = patterns;
+        self
+    }
+
+    #[inline]
+    pub fn strips_trailing_cr(&self) -> bool {
+        self.strip_trailing_cr
+    }
+
+    /// Returns `true` if no difference is ignored, so equal bytes are the only equal lines
+    pub fn is_exact(&self) -> bool {
+        !(self.ignore_all_space
+            || self.ignore_space_change
+            || self.ignore_case
+            || self.strip_trailing_cr
+            || self.ignore_blank_lines)
+            && self.ignore_matching.is_empty()
+    }
+
+    /// Returns the text which is compared instead of the line
+    pub fn key<'a>(&self, line: &'a str) -> Cow<'a, str> {
+        if !(self.ignore_all_space
+            || self.ignore_space_change
+            || self.ignore_case
+            || self.strip_trailing_cr)
+        {
+            return Cow::Borrowed(line);
+        }
+
+        // The terminator is kept, a missing newline at the end of file is still a change
+        let (mut content, terminator) = match line.strip_suffix('\n') {
+            Some(content) => (content, "\n"),
+            None => (line, ""),
+        };
+        if self.strip_trailing_cr {
+            content = content.strip_suffix('\r').unwrap_or(content);
+        }
+
+        let mut key = String::with_capacity(line.len());
+        if self.ignore_all_space {
+            key.extend(content.chars().filter(|c| !c.is_whitespace()));
+        } else if self.ignore_space_change {
+            for (n, word) in content.split_whitespace().enumerate() {
+                if n > 0 || content.starts_with(char::is_whitespace) {
+                    key.push(' ');
+                }
+                key.push_str(word);
+            }
+        } else {
+            key.push_str(content);
+        }
+        if self.ignore_case {
+            key = key.to_lowercase();
+        }
+        key.push_str(terminator);
+        Cow::Owned(key)
+    }
+
+    /// Returns true if a change of this line alone is not reported
+    pub fn is_ignorable(&self, line: &str) -> bool {
+        let content = line.trim_end_matches(['\n', '\r']);
+        (self.ignore_blank_lines && content.trim().is_empty())
+            || self.ignore_matching.iter().any(|re| re.is_match(content))
+    }
+
+    /// Returns true if every changed line of the hunk is ignorable
+    pub fn is_ignorable_hunk(&self, hunk: &Hunk, old_lines: &[&str], new_lines: &[&str]) -> bool {
+        if !self.ignore_blank_lines && self.ignore_matching.is_empty() {
+            return false;
+        }
+        hunk.ops().iter().filter(|op| !op.is_equal()).all(|op| {
+            old_lines[op.old_range()]
+                .iter()
+                .chain(&new_lines[op.new_range()])
+                .all(|line| self.is_ignorable(line))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diff::hunk::group_hunks;
+    use crate::diff::myers;
+
+    #[test]
+    fn keys_ignore_the_chosen_differences() {
+        let all_space = Normalizer::new().ignore_all_space(true);
+        assert_eq!(all_space.key(" a  b\t\n"), all_space.key("ab\n"));
+
+        let space_change = Normalizer::new().ignore_space_change(true);
+        assert_eq!(space_change.key("  a \t b  \n"), " a b\n");
+        assert_ne!(space_change.key("a b\n"), space_change.key("ab\n"));
+
+        let case = Normalizer::new().ignore_case(true);
+        assert_eq!(case.key("Hello World\n"), case.key("hello world\n"));
+
+        let cr = Normalizer::new().strip_trailing_cr(true);
+        assert_eq!(cr.key("line\r\n"), "line\n");
+        // A missing newline at end of file is still a change
+        assert_ne!(cr.key("line\r\n"), cr.key("line"));
+    }
+
+    #[test]
+    fn exact_comparison_keeps_lines() {
+        let exact = Normalizer::new();
+        assert!(exact.is_exact());
+        assert!(matches!(exact.key(" a\r\n"), Cow::Borrowed(" a\r\n")));
+        assert!(!exact.ignore_blank_lines(true).is_exact());
+    }
+
+    #[test]
+    fn hunks_of_ignorable_lines_are_ignored() {
+        let normalizer = Normalizer::new()
+            .ignore_blank_lines(true)
+            .ignore_matching(vec![Regex::new("^// ").unwrap()]);
+        let old = ["a\n", "b\n", "c\n"];
+        let blank = ["a\n", "\n", "b\n", "  \n", "c\n"];
+        let comment = ["a\n", "// note\n", "b\n", "c\n"];
+        let code = ["a\n", "b\n", "x\n", "c\n"];
+        let ignorable = |new: &[&str]| {
+            let hunks = group_hunks(&myers::diff(&old, new), 0);
+            hunks
+                .iter()
+                .all(|hunk| normalizer.is_ignorable_hunk(hunk, &old, new))
+        };
+        assert!(ignorable(&blank));
+        assert!(ignorable(&comment));
+        assert!(!ignorable(&code));
+    }
+}
@@ -0,0 +1,190 @@
+use std::ops::Range;
+
+/// A single operation of an edit script, which turns the old sequence into the new one.
+///
+/// Indices are 0-based positions in the old and the new sequence.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DiffOp {
+    Equal {
+        old_index: usize,
+        new_index: usize,
+        len: usize,
+    },
+    Delete {
+        old_index: usize,
+        old_len: usize,
+        new_index: usize,
+    },
+    Insert {
+        old_index: usize,
+        new_index: usize,
+        new_len: usize,
+    },
+    Replace {
+        old_index: usize,
+        old_len: usize,
+        new_index: usize,
+        new_len: usize,
+    },
+}
+
+impl DiffOp {
+    /// Returns the range of the old sequence covered by this operation
+    pub fn old_range(&self) -> Range<usize> {
+        match *self {
+            DiffOp::Equal { old_index, len, .. } => old_index..old_index + len,
+            DiffOp::Delete {
+                old_index, old_len, ..
+            }
+            | DiffOp::Replace {
+                old_index, old_len, ..
+            } => old_index..old_index + old_len,
+            DiffOp::Insert { old_index, .. } => old_index..old_index,
+        }
+    }
+
+    /// Returns the range of the new sequence covered by this operation
+    pub fn new_range(&self) -> Range<usize> {
+        match *self {
+            DiffOp::Equal { new_index, len, .. } => new_index..new_index + len,
+            DiffOp::Insert {
+                new_index, new_len, ..
+            }
+            | DiffOp::Replace {
+                new_index, new_len, ..
+            } => new_index..new_index + new_len,
+            DiffOp::Delete { new_index, .. } => new_index..new_index,
+        }
+    }
+
+    #[inline]
+    pub fn is_equal(&self) -> bool {
+        matches!(self, DiffOp::Equal { .. })
+    }
+
+    /// Builds the operation which covers the given ranges, an empty range on one side
+    /// becomes an insertion or a deletion.
+    pub fn from_ranges(old: Range<usize>, new: Range<usize>) -> DiffOp {
+        match (old.is_empty(), new.is_empty()) {
+            (true, _) => DiffOp::Insert {
+                old_index: old.start,
+                new_index: new.start,
+                new_len: new.len(),
+            },
+            (false, true) => DiffOp::Delete {
+                old_index: old.start,
+                old_len: old.len(),
+                new_index: new.start,
+            },
+            (false, false) => DiffOp::Replace {
+                old_index: old.start,
+                old_len: old.len(),
+                new_index: new.start,
+                new_len: new.len(),
+            },
+        }
+    }
+}
+
+/// Collects the operations emitted by a diff algorithm.
+///
+/// Adjacent operations of the same kind are merged, and a deletion directly followed
+/// by an insertion (or vice versa) becomes a replacement.
+#[derive(Debug, Default)]
+pub struct Capture {
+    ops: Vec<DiffOp>,
+}
+
+impl Capture {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn equal(&mut self, old_index: usize, new_index: usize, len: usize) {
+        if len == 0 {
+            return;
+        }
+        if let Some(DiffOp::Equal { len: last_len, .. }) = self.ops.last_mut() {
+            *last_len += len;
+            return;
+        }
+        self.ops.push(DiffOp::Equal {
+            old_index,
+            new_index,
+            len,
+        });
+    }
+
+    pub fn delete(&mut self, old_index: usize, old_len: usize, new_index: usize) {
+        if old_len == 0 {
+            return;
+        }
+        self.change(old_index..old_index + old_len, new_index..new_index);
+    }
+
+    pub fn insert(&mut self, old_index: usize, new_index: usize, new_len: usize) {
+        if new_len == 0 {
+            return;
+        }
+        self.change(old_index..old_index, new_index..new_index + new_len);
+    }
+
+    fn change(&mut self, old: Range<usize>, new: Range<usize>) {
+        if let Some(last) = self.ops.last_mut() {
+            if !last.is_equal() {
+                let (last_old, last_new) = (last.old_range(), last.new_range());
+                *last = DiffOp::from_ranges(
+                    last_old.start..last_old.end + old.len(),
+                    last_new.start..last_new.end + new.len(),
+                );
+                return;
+            }
+        }
+        self.ops.push(DiffOp::from_ranges(old, new));
+    }
+
+    pub fn into_ops(self) -> Vec<DiffOp> {
+        self.ops
+    }
+}
+
+/// Returns the length of the common prefix of the two slices
+pub fn common_prefix_len<T: PartialEq>(old: &[T], new: &[T]) -> usize {
+    old.iter().zip(new).take_while(|(a, b)| a == b).count()
+}
+
+/// Returns the length of the common suffix of the two slices
+pub fn common_suffix_len<T: PartialEq>(old: &[T], new: &[T]) -> usize {
+    old.iter()
+        .rev()
+        .zip(new.iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count()
+}
+
+/// Rebuilds the new sequence from `old` and the edit script, only the inserted and the
+/// replacing items are taken from `new`. Panics if the script does not cover both
+/// sequences in order.
+#[cfg(test)]
+pub fn apply<T: Clone>(ops: &[DiffOp], old: &[T], new: &[T]) -> Vec<T> {
+    let (mut i, mut j) = (0, 0);
+    let mut result = Vec::with_capacity(new.len());
+    for op in ops {
+        let (old_range, new_range) = (op.old_range(), op.new_range());
+        assert_eq!(
+            (old_range.start, new_range.start),
+            (i, j),
+            "{:?} is out of order",
+            op
+        );
+        if op.is_equal() {
+            result.extend_from_slice(&old[old_range.clone()]);
+        } else {
+            result.extend_from_slice(&new[new_range.clone()]);
+        }
+        i = old_range.end;
+        j = new_range.end;
+    }
+    assert_eq!((i, j), (old.len(), new.len()), "{:?} leaves items out", ops);
+    result
+}
@@ -0,0 +1,159 @@
+//! Patience diff, the lines which occur exactly once on both sides are used as anchors.
+//!
+//! The longest increasing sequence of the anchors divides the problem, ranges without
+//! any anchor are compared with Myers.
+
+use super::myers;
+use super::ops::{common_prefix_len, common_suffix_len, Capture};
+use std::collections::HashMap;
+use std::hash::Hash;
+
+pub fn diff_into<T: Hash + Eq>(
+    old: &[T],
+    old_start: usize,
+    new: &[T],
+    new_start: usize,
+    capture: &mut Capture,
+) {
+    let prefix = common_prefix_len(old, new);
+    capture.equal(old_start, new_start, prefix);
+    let (old, new) = (&old[prefix..], &new[prefix..]);
+    let (old_start, new_start) = (old_start + prefix, new_start + prefix);
+
+    let suffix = common_suffix_len(old, new);
+    let (old, new) = (&old[..old.len() - suffix], &new[..new.len() - suffix]);
+
+    if old.is_empty() || new.is_empty() {
+        capture.delete(old_start, old.len(), new_start);
+        capture.insert(old_start + old.len(), new_start, new.len());
+    } else {
+        let anchors = unique_anchors(old, new);
+        if anchors.is_empty() {
+            myers::diff_into(old, old_start, new, new_start, false, capture);
+        } else {
+            let (mut i, mut j) = (0, 0);
+            for (x, y) in anchors {
+                diff_into(
+                    &old[i..x],
+                    old_start + i,
+                    &new[j..y],
+                    new_start + j,
+                    capture,
+                );
+                capture.equal(old_start + x, new_start + y, 1);
+                i = x + 1;
+                j = y + 1;
+            }
+            diff_into(&old[i..], old_start + i, &new[j..], new_start + j, capture);
+        }
+    }
+
+    capture.equal(old_start + old.len(), new_start + new.len(), suffix);
+}
+
+/// Returns the pairs of indices of lines which are unique on both sides, the pairs
+/// form the longest sequence which is increasing on both sides.
+fn unique_anchors<T: Hash + Eq>(old: &[T], new: &[T]) -> Vec<(usize, usize)> {
+    // Occurrences on each side and the index of the last one
+    let mut counts: HashMap<&T, (usize, usize, usize, usize)> = HashMap::new();
+    for (i, line) in old.iter().enumerate() {
+        let entry = counts.entry(line).or_insert((0, 0, 0, 0));
+        entry.0 += 1;
+        entry.1 = i;
+    }
+    for (j, line) in new.iter().enumerate() {
+        if let Some(entry) = counts.get_mut(line) {
+            entry.2 += 1;
+            entry.3 = j;
+        }
+    }
+
+    let mut pairs: Vec<(usize, usize)> = counts
+        .values()
+        .filter(|&&(old_count, _, new_count, _)| old_count == 1 && new_count == 1)
+        .map(|&(_, i, _, j)| (i, j))
+        .collect();
+    pairs.sort_unstable();
+    longest_increasing(&pairs)
+}
+
+/// Patience sorting of the pairs by their second index, the pairs are sorted by the
+/// first index.
+fn longest_increasing(pairs: &[(usize, usize)]) -> Vec<(usize, usize)> {
+    // The top of each pile and the pair before it in the sequence
+    let mut piles: Vec<usize> = Vec::new();
+    let mut previous: Vec<Option<usize>> = vec![None; pairs.len()];
+    for (n, &(_, j)) in pairs.iter().enumerate() {
+        let pile = piles.partition_point(|&top| pairs[top].1 < j);
+        if pile > 0 {
+            previous[n] = Some(piles[pile - 1]);
+        }
+        if pile == piles.len() {
+            piles.push(n);
+        } else {
+            piles[pile] = n;
+        }
+    }
+
+    let mut sequence = Vec::with_capacity(piles.len());
+    let mut current = piles.last().copied();
+    while let Some(n) = current {
+        sequence.push(pairs[n]);
+        current = previous[n];
+    }
+    sequence.reverse();
+    sequence
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diff::ops::{apply, DiffOp};
+
+    fn diff(old: &[&str], new: &[&str]) -> Vec<DiffOp> {
+        let mut capture = Capture::new();
+        diff_into(old, 0, new, 0, &mut capture);
+        capture.into_ops()
+    }
+
+    #[test]
+    fn script_turns_old_into_new() {
+        let cases: &[(&[&str], &[&str])] = &[
+            (&[], &["a"]),
+            (&["a"], &[]),
+            (&["a", "b", "c"], &["c", "b", "a"]),
+            (
+                &["}", "fn a", "}", "fn b", "}"],
+                &["}", "fn b", "}", "fn c", "}"],
+            ),
+            (&["x", "a", "x", "b", "x"], &["b", "x", "a", "x"]),
+        ];
+        for (old, new) in cases {
+            assert_eq!(apply(&diff(old, new), old, new), *new);
+        }
+    }
+
+    #[test]
+    fn unique_lines_are_kept_in_place() {
+        // `fn a` is moved, the longest sequence of anchors keeps `fn b` and `fn c`
+        let old = ["fn a", "{", "fn b", "{", "fn c"];
+        let new = ["fn b", "{", "fn c", "{", "fn a"];
+        assert_eq!(unique_anchors(&old, &new), vec![(2, 0), (4, 2)]);
+        assert_eq!(apply(&diff(&old, &new), &old, &new), new);
+    }
+
+    #[test]
+    fn longest_increasing_sequence() {
+        let pairs = [(0, 3), (1, 0), (2, 1), (3, 4), (4, 2)];
+        assert_eq!(longest_increasing(&pairs), vec![(1, 0), (2, 1), (4, 2)]);
+        assert_eq!(longest_increasing(&[]), vec![]);
+    }
+
+    #[test]
+    fn without_unique_lines_falls_back_to_myers() {
+        let old = ["a", "a", "b", "b"];
+        let new = ["b", "b", "a", "a"];
+        assert!(unique_anchors(&old, &new).is_empty());
+        assert_eq!(diff(&old, &new), myers::diff(&old, &new));
+    }
+}
@@ -0,0 +1,280 @@
+//! Rename and copy detection of directory diffs.
+//!
+//! A file which exists only in the left tree and a file which exists only in the right
+//! tree are a rename if their contents are similar enough. Like git, the similarity is
+//! the share of the bytes of the larger file which are found in the other file, counted
+//! by whole lines.
+
+use super::dir::{self, Change};
+use crate::walk::Walk;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+
+/// The threshold used by `--find-renames` without a value
+pub const DEFAULT_THRESHOLD: usize = 50;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum RenameKind {
+    Renamed,
+    Copied,
+}
+
+/// A file of the left tree which is found at another path in the right tree, paths are
+/// relative to the compared roots
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Rename {
+    pub kind: RenameKind,
+    pub old: PathBuf,
+    pub new: PathBuf,
+    /// The similarity in percent
+    pub similarity: usize,
+}
+
+/// The lines of a file, by the hashes of their contents
+struct Fingerprint {
+    size: usize,
+    /// The number of bytes of all lines with the same hash
+    lines: HashMap<u64, usize>,
+}
+
+impl Fingerprint {
+    fn read(path: &Path) -> Option<Self> {
+        let bytes = fs::read(path).ok()?;
+        let mut lines: HashMap<u64, usize> = HashMap::new();
+        for line in bytes.split_inclusive(|&b| b == b'\n') {
+            let mut hasher = DefaultHasher::new();
+            line.hash(&mut hasher);
+            *lines.entry(hasher.finish()).or_default() += line.len();
+        }
+        Some(Self {
+            size: bytes.len(),
+            lines,
+        })
+    }
+
+    /// Returns the similarity in percent
+    fn similarity(&self, other: &Fingerprint) -> usize {
+        let larger = self.size.max(other.size);
+        if larger == 0 {
+            return 100;
+        }
+        let common: usize = self
+            .lines
+            .iter()
+            .filter_map(|(hash, &n)| Some(n.min(*other.lines.get(hash)?)))
+            .sum();
+        common * 100 / larger
+    }
+}
+
+/// Finds the renamed files among the entries which exist on one side only, the files
+/// in one-sided directories take part too. With `copies` the remaining right-only files
+/// are also compared to the modified files and the sources of renames, a match is a copy.
+pub fn find_renames(
+    left: &Path,
+    right: &Path,
+    changes: &[Change],
+    walk: impl Fn() -> Walk,
+    threshold: usize,
+    copies: bool,
+) -> Vec<Rename> {
+    let relative_files = |root: &Path, path: &Path| -> Vec<PathBuf> {
+        dir::files_in(&root.join(path), walk())
+            .into_iter()
+            .filter_map(|file| Some(file.strip_prefix(root).ok()?.to_path_buf()))
+            .collect()
+    };
+    let (mut deleted, mut added, mut modified) = (Vec::new(), Vec::new(), Vec::new());
+    for change in changes {
+        match change {
+            Change::LeftOnly(p) => deleted.extend(relative_files(left, p)),
+            Change::RightOnly(p) => added.extend(relative_files(right, p)),
+            Change::Modified(p) => modified.push(p.clone()),
+            Change::TypeChanged(_) | Change::LinkChanged(_) => {}
+        }
+    }
+    if added.is_empty() || (deleted.is_empty() && !copies) {
+        return vec![];
+    }
+
+    let fingerprints = |root: &Path, paths: Vec<PathBuf>| -> Vec<(PathBuf, Fingerprint)> {
+        paths
+            .into_iter()
+            .filter_map(|p| {
+                let fingerprint = Fingerprint::read(&root.join(&p))?;
+                Some((p, fingerprint))
+            })
+            // Empty files are all alike, they are never renames of each other
+            .filter(|(_, fingerprint)| fingerprint.size > 0)
+            .collect()
+    };
+    let deleted = fingerprints(left, deleted);
+    let added = fingerprints(right, added);
+
+    let mut renames = Vec::new();
+    let mut matched = HashSet::new();
+    for (i, j, similarity) in best_pairs(&deleted, &added, threshold) {
+        matched.insert(j);
+        renames.push(Rename {
+            kind: RenameKind::Renamed,
+            old: deleted[i].0.clone(),
+            new: added[j].0.clone(),
+            similarity,
+        });
+    }
+
+    if copies {
+        let mut sources = fingerprints(left, modified);
+        sources.extend(deleted);
+        let remaining: Vec<(PathBuf, Fingerprint)> = added
+            .into_iter()
+            .enumerate()
+            .filter(|(j, _)| !matched.contains(j))
+            .map(|(_, file)| file)
+            .collect();
+        // A source can be copied many times, each copy takes its most similar source
+        for (path, fingerprint) in &remaining {
+            let best = sources
+                .iter()
+                .map(|(source, f)| (f.similarity(fingerprint), source))
+                .filter(|&(similarity, _)| similarity >= threshold)
+                .max_by_key(|&(similarity, source)| (similarity, std::cmp::Reverse(source)));
+            if let Some((similarity, source)) = best {
+                renames.push(Rename {
+                    kind: RenameKind::Copied,
+                    old: source.clone(),
+                    new: path.clone(),
+                    similarity,
+                });
+            }
+        }
+    }
+    renames.sort_by(|a, b| a.new.cmp(&b.new));
+    renames
+}
+
+/// Pairs each deleted file with at most one added file, the most similar pairs first.
+/// Files with the same name win ties.
+fn best_pairs(
+    deleted: &[(PathBuf, Fingerprint)],
+    added: &[(PathBuf, Fingerprint)],
+    threshold: usize,
+) -> Vec<(usize, usize, usize)> {
+    let mut candidates = Vec::new();
+    for (i, (old, old_print)) in deleted.iter().enumerate() {
+        for (j, (new, new_print)) in added.iter().enumerate() {
+            // The common bytes are at most the size of the smaller file
+            let (small, large) = (
+                old_print.size.min(new_print.size),
+                old_print.size.max(new_print.size),
+            );
+            if large > 0 && small * 100 < threshold * large {
+                continue;
+            }
+            let similarity = old_print.similarity(new_print);
+            if similarity >= threshold {
+                let same_name = old.file_name() == new.file_name();
+                candidates.push((similarity, same_name, i, j));
+            }
+        }
+    }
+    candidates.sort_by(|a, b| {
+        (b.0, b.1)
+            .cmp(&(a.0, a.1))
+            .then((a.2, a.3).cmp(&(b.2, b.3)))
+    });
+
+    let (mut used_old, mut used_new) = (HashSet::new(), HashSet::new());
+    let mut pairs = Vec::new();
+    for (similarity, _, i, j) in candidates {
+        if used_old.contains(&i) || used_new.contains(&j) {
+            continue;
+        }
+        used_old.insert(i);
+        used_new.insert(j);
+        pairs.push((i, j, similarity));
+    }
+    pairs
+}
+
+/// Parses a threshold like `50%` or `50`, in percent
+pub fn parse_threshold(value: &str) -> Option<usize> {
+    let value = value.trim();
+    let number = value.strip_suffix('%').unwrap_or(value);
+    number.trim().parse().ok().filter(|&n| n <= 100)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    fn lines(prefix: &str, n: usize) -> String {
+        (1..=n).map(|i| format!("{} {}\n", prefix, i)).collect()
+    }
+
+    fn tree(name: &str, files: &[(&str, String)]) -> PathBuf {
+        let root =
+            env::temp_dir().join(format!("show-rename-test-{}-{}", std::process::id(), name));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        for (path, content) in files {
+            fs::write(root.join(path), content).unwrap();
+        }
+        root
+    }
+
+    #[test]
+    fn renames_and_copies_are_found() {
+        let left = tree(
+            "left",
+            &[
+                ("old.txt", lines("line", 10)),
+                ("mod.txt", lines("mod", 10)),
+            ],
+        );
+        let right = tree(
+            "right",
+            &[
+                ("new.txt", lines("line", 9) + "changed\n"),
+                ("mod.txt", lines("mod", 11)),
+                ("copy.txt", lines("mod", 10)),
+                ("other.txt", lines("other", 10)),
+            ],
+        );
+        let changes: Vec<Change> = dir::compare_dirs(&left, &right, Walk::new).collect();
+        let rename = |kind, old: &str, new: &str, similarity| Rename {
+            kind,
+            old: old.into(),
+            new: new.into(),
+            similarity,
+        };
+        // The last 8 of the 71 bytes differ
+        let renamed = rename(RenameKind::Renamed, "old.txt", "new.txt", 88);
+        assert_eq!(
+            find_renames(&left, &right, &changes, Walk::new, DEFAULT_THRESHOLD, true),
+            [
+                rename(RenameKind::Copied, "mod.txt", "copy.txt", 100),
+                renamed.clone()
+            ]
+        );
+        assert_eq!(
+            find_renames(&left, &right, &changes, Walk::new, DEFAULT_THRESHOLD, false),
+            [renamed]
+        );
+        assert!(find_renames(&left, &right, &changes, Walk::new, 90, false).is_empty());
+        fs::remove_dir_all(&left).unwrap();
+        fs::remove_dir_all(&right).unwrap();
+    }
+
+    #[test]
+    fn thresholds_are_percentages() {
+        assert_eq!(parse_threshold("75%"), Some(75));
+        assert_eq!(parse_threshold(" 40 "), Some(40));
+        assert_eq!(parse_threshold("101"), None);
+        assert_eq!(parse_threshold("half"), None);
+    }
+}
@@ -0,0 +1,236 @@
+//! Set comparison of the lines of two files, like comm(1) but without sorted input.
+//!
+//! Lines are matched by their normalized keys, so `--ignore-case` and the other ignore
+//! options apply. Each group lists its lines in the order they first appear.
+
+use super::normalize::Normalizer;
+use super::paint;
+use ansi_term::{Color::*, Style};
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::str::FromStr;
+
+/// A group of lines of the set comparison
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Group {
+    Left,
+    Right,
+    Both,
+}
+
+impl FromStr for Group {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "left" => Ok(Group::Left),
+            "right" => Ok(Group::Right),
+            "both" => Ok(Group::Both),
+            _ => Err(format!("Unknown group {}", s)),
+        }
+    }
+}
+
+/// A distinct line and how often it appears in each file
+struct Entry<'a> {
+    line: &'a str,
+    left: usize,
+    right: usize,
+}
+
+/// The lines of both files grouped by where they appear
+pub struct LineSets<'a> {
+    entries: Vec<Entry<'a>>,
+}
+
+impl<'a> LineSets<'a> {
+    /// Groups the lines by their keys, ignorable lines are skipped
+    pub fn new(old_lines: &[&'a str], new_lines: &[&'a str], normalizer: &Normalizer) -> Self {
+        let mut index: HashMap<String, usize> = HashMap::new();
+        let mut entries: Vec<Entry<'a>> = Vec::new();
+        let mut add = |line: &'a str, left: usize, right: usize| {
+            let line = line.trim_end_matches(['\n', '\r']);
+            if normalizer.is_ignorable(line) {
+                return;
+            }
+            let key = normalizer.key(line).into_owned();
+            let next = entries.len();
+            let i = *index.entry(key).or_insert(next);
+            if i == next {
+                entries.push(Entry {
+                    line,
+                    left: 0,
+                    right: 0,
+                });
+            }
+            entries[i].left += left;
+            entries[i].right += right;
+        };
+        old_lines.iter().for_each(|line| add(line, 1, 0));
+        new_lines.iter().for_each(|line| add(line, 0, 1));
+        Self { entries }
+    }
+
+    /// Returns the lines of a group and their counts. With `multiset` each occurrence
+    /// counts, so a line which is twice in the left file and once in the right file is
+    /// once in both and once only in the left file. Otherwise each count is 1.
+    fn group(&self, group: Group, multiset: bool) -> Vec<(&'a str, usize)> {
+        self.entries
+            .iter()
+            .filter_map(|e| {
+                let common = e.left.min(e.right);
+                let count = match (group, multiset) {
+                    (Group::Left, true) => e.left - common,
+                    (Group::Right, true) => e.right - common,
+                    (Group::Both, true) => common,
+                    (Group::Left, false) => (e.right == 0) as usize,
+                    (Group::Right, false) => (e.left == 0) as usize,
+                    (Group::Both, false) => (common > 0) as usize,
+                };
+                Some((e.line, count)).filter(|&(_, count)| count > 0)
+            })
+            .collect()
+    }
+
+    /// Returns `true` if a line is in one file only, or with `multiset` if a line is
+    /// more often in one file than in the other
+    pub fn differ(&self, multiset: bool) -> bool {
+        self.entries.iter().any(|e| {
+            if multiset {
+                e.left != e.right
+            } else {
+                (e.left == 0) != (e.right == 0)
+            }
+        })
+    }
+}
+
+/// Prints the groups of a set comparison, each under a heading with its size
+pub struct SetPrinter {
+    color: bool,
+    hidden: Vec<Group>,
+    counts: bool,
+}
+
+impl SetPrinter {
+    pub fn new(color: bool) -> Self {
+        Self {
+            color,
+            hidden: vec![],
+            counts: false,
+        }
+    }
+
+    /// Leaves out the groups, like `comm -1 -2 -3` does
+    pub fn with_hidden(mut self, hidden: Vec<Group>) -> Self {
+        self.hidden = hidden;
+        self
+    }
+
+    /// Counts every occurrence of a line and prints the counts
+    pub fn with_counts(mut self, counts: bool) -> Self {
+        self.counts = counts;
+        self
+    }
+
+    pub fn write_sets<W: Write>(
+        &self,
+        out: &mut W,
+        sets: &LineSets,
+        old_name: &str,
+        new_name: &str,
+    ) -> io::Result<()> {
+        let groups = [
+            (Group::Left, format!("Only in {}", old_name), Red.normal()),
+            (
+                Group::Right,
+                format!("Only in {}", new_name),
+                Green.normal(),
+            ),
+            (Group::Both, "In both".to_string(), Style::new()),
+        ];
+        for (group, title, style) in groups {
+            if self.hidden.contains(&group) {
+                continue;
+            }
+            let lines = sets.group(group, self.counts);
+            let total: usize = lines.iter().map(|(_, count)| count).sum();
+            let title = format!("{} ({}):", title, total);
+            writeln!(out, "{}", paint(self.color, Style::new().bold(), &title))?;
+            let width = lines
+                .iter()
+                .map(|(_, c)| c.to_string().len())
+                .max()
+                .unwrap_or(0);
+            for (line, count) in lines {
+                let text = if self.counts {
+                    format!("  {:>w$}  {}", count, line, w = width)
+                } else {
+                    format!("  {}", line)
+                };
+                writeln!(out, "{}", paint(self.color, style, &text))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OLD: &[&str] = &["b\n", "a\n", "a\n", "c\r\n"];
+    const NEW: &[&str] = &["A\n", "d\n", "b\n"];
+
+    fn output(printer: SetPrinter) -> String {
+        let sets = LineSets::new(OLD, NEW, &Normalizer::new().ignore_case(true));
+        let mut out = Vec::new();
+        printer.write_sets(&mut out, &sets, "old", "new").unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn lines_are_grouped_in_order_of_appearance() {
+        assert_eq!(
+            output(SetPrinter::new(false)),
+            "\
+Only in old (1):
+  c
+Only in new (1):
+  d
+In both (2):
+  b
+  a
+"
+        );
+    }
+
+    #[test]
+    fn counts_make_a_multiset() {
+        assert_eq!(
+            output(
+                SetPrinter::new(false)
+                    .with_counts(true)
+                    .with_hidden(vec![Group::Right])
+            ),
+            "\
+Only in old (2):
+  1  a
+  1  c
+In both (2):
+  1  b
+  1  a
+"
+        );
+    }
+
+    #[test]
+    fn sets_differ_by_lines_or_counts() {
+        let normalizer = Normalizer::new();
+        let sets = LineSets::new(&["a\n", "a\n"], &["a\n"], &normalizer);
+        assert!(!sets.differ(false));
+        assert!(sets.differ(true));
+        assert_eq!("both".parse(), Ok(Group::Both));
+        assert!("all".parse::<Group>().is_err());
+    }
+}
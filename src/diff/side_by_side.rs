@@ -0,0 +1,284 @@
+//! Side by side output like `diff -y`, the old file in the left column and the new file
+//! in the right one.
+//!
+//! The gutter between the columns marks each row, `|` for changed, `<` for deleted and
+//! `>` for inserted lines. Lines longer than a column are cut or wrapped.
+
+use super::funcname::FunctionMatcher;
+use super::hunk::Hunk;
+use super::ops::DiffOp;
+use super::unified::format_range;
+use super::{paint, DEFAULT_WIDTH};
+use ansi_term::{Color::*, Style};
+use std::io::{self, Write};
+use unicode_width::UnicodeWidthChar;
+
+/// The narrowest column which is still readable
+const MIN_COLUMN_WIDTH: usize = 16;
+
+const TAB_WIDTH: usize = 8;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum RowKind {
+    Equal,
+    Changed,
+    Deleted,
+    Added,
+}
+
+impl RowKind {
+    fn gutter(self) -> char {
+        match self {
+            RowKind::Equal => ' ',
+            RowKind::Changed => '|',
+            RowKind::Deleted => '<',
+            RowKind::Added => '>',
+        }
+    }
+}
+
+/// Prints hunks in two columns, the old file on the left and the new file on the right
+pub struct SideBySidePrinter {
+    color: bool,
+    width: usize,
+    wrap: bool,
+    functions: Option<FunctionMatcher>,
+}
+
+impl SideBySidePrinter {
+    pub fn new(color: bool) -> Self {
+        Self {
+            color,
+            width: DEFAULT_WIDTH,
+            wrap: false,
+            functions: None,
+        }
+    }
+
+    /// Sets the total width of both columns and the gutter
+    pub fn with_width(mut self, width: usize) -> Self {
+        self.width = width;
+        self
+    }
+
+    /// Wraps long lines instead of truncating them
+    pub fn with_wrap(mut self, wrap: bool) -> Self {
+        self.wrap = wrap;
+        self
+    }
+
+    /// Names the function or section a hunk is in, in the header above its rows
+    pub fn with_functions(mut self, functions: FunctionMatcher) -> Self {
+        self.functions = Some(functions);
+        self
+    }
+
+    #[inline]
+    fn column_width(&self) -> usize {
+        (self.width.saturating_sub(3) / 2).max(MIN_COLUMN_WIDTH)
+    }
+
+    pub fn write_header<W: Write>(
+        &self,
+        out: &mut W,
+        old_name: &str,
+        new_name: &str,
+    ) -> io::Result<()> {
+        let column = self.column_width();
+        let left = fit(old_name, column, false).remove(0);
+        let right = fit(new_name, column, false).remove(0);
+        let header = format!("{}   {}", pad(&left, column), right);
+        writeln!(out, "{}", paint(self.color, Style::new().bold(), header))
+    }
+
+    pub fn write_hunk<W: Write>(
+        &self,
+        out: &mut W,
+        hunk: &Hunk,
+        old_lines: &[&str],
+        new_lines: &[&str],
+    ) -> io::Result<()> {
+        let (old_range, new_range) = (hunk.old_range(), hunk.new_range());
+        let mut header = format!(
+            "@@ -{} +{} @@",
+            format_range(hunk.old_range()),
+            format_range(hunk.new_range())
+        );
+        let function = self
+            .functions
+            .as_ref()
+            .and_then(|f| f.find(old_lines, old_range.start));
+        if let Some(function) = function {
+            header = format!("{} {}", header, function);
+        }
+        writeln!(out, "{}", paint(self.color, Cyan.normal(), header))?;
+
+        let number_width = old_range.end.max(new_range.end).to_string().len();
+        for op in hunk.ops() {
+            let (old, new) = (op.old_range(), op.new_range());
+            let kind = match op {
+                DiffOp::Equal { .. } => RowKind::Equal,
+                _ => RowKind::Changed,
+            };
+            for n in 0..old.len().max(new.len()) {
+                let left = old.clone().nth(n);
+                let right = new.clone().nth(n);
+                let kind = match (left, right) {
+                    (Some(_), None) => RowKind::Deleted,
+                    (None, Some(_)) => RowKind::Added,
+                    _ => kind,
+                };
+                self.write_row(
+                    out,
+                    kind,
+                    number_width,
+                    left.map(|i| (i + 1, old_lines[i])),
+                    right.map(|j| (j + 1, new_lines[j])),
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    fn write_row<W: Write>(
+        &self,
+        out: &mut W,
+        kind: RowKind,
+        number_width: usize,
+        left: Option<(usize, &str)>,
+        right: Option<(usize, &str)>,
+    ) -> io::Result<()> {
+        let column = self.column_width();
+        let text_width = column.saturating_sub(number_width + 1).max(1);
+        let cells = |line: Option<(usize, &str)>| -> Vec<String> {
+            match line {
+                Some((number, text)) => fit(text, text_width, self.wrap)
+                    .into_iter()
+                    .enumerate()
+                    .map(|(n, part)| {
+                        if n == 0 {
+                            format!("{:>w$} {}", number, part, w = number_width)
+                        } else {
+                            format!("{:>w$} {}", "", part, w = number_width)
+                        }
+                    })
+                    .collect(),
+                None => vec![],
+            }
+        };
+        let left_cells = cells(left);
+        let right_cells = cells(right);
+
+        let (left_style, right_style) = match kind {
+            RowKind::Equal => (Style::new(), Style::new()),
+            _ => (Red.normal(), Green.normal()),
+        };
+        for n in 0..left_cells.len().max(right_cells.len()) {
+            let left = left_cells.get(n).map_or(String::new(), |c| c.clone());
+            let right = right_cells.get(n).map_or(String::new(), |c| c.clone());
+            writeln!(
+                out,
+                "{} {} {}",
+                paint(self.color, left_style, pad(&left, column)),
+                paint(self.color, Style::new().bold(), kind.gutter().to_string()),
+                paint(self.color, right_style, right)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Pads the text with spaces to the display width
+fn pad(text: &str, width: usize) -> String {
+    let used: usize = text.chars().filter_map(UnicodeWidthChar::width).sum();
+    format!("{}{}", text, " ".repeat(width.saturating_sub(used)))
+}
+
+/// Splits the line into parts which fit into the display width, tabs are expanded.
+/// Without `wrap` only the first part is returned, ending with `>` if it was cut.
+fn fit(line: &str, width: usize, wrap: bool) -> Vec<String> {
+    let line = line.trim_end_matches(['\n', '\r']);
+    let mut parts = vec![];
+    let mut part = String::new();
+    let mut used = 0;
+    for c in line.chars() {
+        let (text, w) = if c == '\t' {
+            let spaces = TAB_WIDTH - used % TAB_WIDTH;
+            (" ".repeat(spaces), spaces)
+        } else {
+            (c.to_string(), c.width().unwrap_or(0))
+        };
+        if used + w > width {
+            if !wrap {
+                // Leave room for the truncation marker
+                while used + 1 > width {
+                    match part.pop() {
+                        Some(c) => used -= c.width().unwrap_or(0),
+                        None => break,
+                    }
+                }
+                part.push('>');
+                return vec![part];
+            }
+            parts.push(std::mem::take(&mut part));
+            used = 0;
+        }
+        part.push_str(&text);
+        used += w;
+    }
+    parts.push(part);
+    parts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diff::hunk::group_hunks;
+    use crate::diff::myers;
+
+    fn columns(old: &[&str], new: &[&str], context: usize) -> String {
+        let printer = SideBySidePrinter::new(false).with_width(40);
+        let mut out = Vec::new();
+        for hunk in group_hunks(&myers::diff(old, new), context) {
+            printer.write_hunk(&mut out, &hunk, old, new).unwrap();
+        }
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn rows_are_marked_in_the_gutter() {
+        let old = ["a\n", "b\n", "c\n", "d\n"];
+        let new = ["a\n", "B\n", "c\n", "e\n", "f\n"];
+        assert_eq!(
+            columns(&old, &new, 1),
+            "\
+@@ -1,4 +1,5 @@
+1 a                  1 a
+2 b                | 2 B
+3 c                  3 c
+4 d                | 4 e
+                   > 5 f
+"
+        );
+    }
+
+    #[test]
+    fn empty_ranges_start_before_the_first_line() {
+        assert_eq!(
+            columns(&[], &["x\n"], 3),
+            "\
+@@ -0,0 +1 @@
+                   > 1 x
+"
+        );
+    }
+
+    #[test]
+    fn long_lines_are_cut_or_wrapped() {
+        assert_eq!(fit("abcdefgh\n", 5, false), ["abcd>"]);
+        assert_eq!(fit("abcdefgh\n", 5, true), ["abcde", "fgh"]);
+        assert_eq!(fit("a\tb", 16, false), ["a       b"]);
+        // Wide characters take two cells
+        assert_eq!(fit("日本語", 4, true), ["日本", "語"]);
+    }
+}
@@ -0,0 +1,300 @@
+use super::hunk::Hunk;
+use super::DEFAULT_WIDTH;
+use ansi_term::{Color, Color::*};
+use std::io::{self, Write};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+/// The narrowest name column, longer names are shortened from the left
+const MIN_NAME_WIDTH: usize = 10;
+
+/// The narrowest histogram bar
+const MIN_BAR_WIDTH: usize = 6;
+
+/// How the change statistics are printed
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum StatFormat {
+    /// Counts with a histogram bar, like `git diff --stat`
+    Stat,
+    /// Tab separated counts, like `git diff --numstat`
+    NumStat,
+}
+
+/// The number of changed lines of a file
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FileStat {
+    name: String,
+    insertions: usize,
+    deletions: usize,
+    /// The old and the new size of a binary file
+    binary: Option<(usize, usize)>,
+}
+
+impl FileStat {
+    pub fn from_hunks(name: String, hunks: &[Hunk]) -> Self {
+        let changes = hunks
+            .iter()
+            .flat_map(|h| h.ops())
+            .filter(|op| !op.is_equal());
+        let (mut insertions, mut deletions) = (0, 0);
+        for op in changes {
+            insertions += op.new_range().len();
+            deletions += op.old_range().len();
+        }
+        Self {
+            name,
+            insertions,
+            deletions,
+            binary: None,
+        }
+    }
+
+    pub fn binary(name: String, old_size: usize, new_size: usize) -> Self {
+        Self {
+            name,
+            insertions: 0,
+            deletions: 0,
+            binary: Some((old_size, new_size)),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn insertions(&self) -> usize {
+        self.insertions
+    }
+
+    pub fn deletions(&self) -> usize {
+        self.deletions
+    }
+}
+
+/// Prints the statistics as names and histogram bars scaled to the width
+pub struct StatPrinter {
+    color: bool,
+    width: usize,
+}
+
+impl StatPrinter {
+    pub fn new(color: bool) -> Self {
+        Self {
+            color,
+            width: DEFAULT_WIDTH,
+        }
+    }
+
+    /// Sets the width of the whole line
+    pub fn with_width(mut self, width: usize) -> Self {
+        self.width = width;
+        self
+    }
+
+    pub fn write_stat<W: Write>(&self, out: &mut W, stats: &[FileStat]) -> io::Result<()> {
+        if stats.is_empty() {
+            return Ok(());
+        }
+
+        let counts: Vec<String> = stats
+            .iter()
+            .map(|s| match s.binary {
+                Some(_) => "Bin".to_string(),
+                None => (s.insertions + s.deletions).to_string(),
+            })
+            .collect();
+        let count_width = counts.iter().map(String::len).max().unwrap_or(0);
+        let max_name = stats.iter().map(|s| s.name.width()).max().unwrap_or(0);
+        // " name | count bar"
+        let name_width = max_name.min(
+            self.width
+                .saturating_sub(count_width + MIN_BAR_WIDTH + 5)
+                .max(MIN_NAME_WIDTH),
+        );
+        let bar_width = self
+            .width
+            .saturating_sub(name_width + count_width + 5)
+            .max(MIN_BAR_WIDTH);
+        let max_change = stats
+            .iter()
+            .map(|s| s.insertions + s.deletions)
+            .max()
+            .unwrap_or(0);
+
+        for (stat, count) in stats.iter().zip(&counts) {
+            let name = shorten(&stat.name, name_width);
+            let padding = " ".repeat(name_width.saturating_sub(name.width()));
+            let graph = match stat.binary {
+                Some((old_size, new_size)) => format!("{} -> {} bytes", old_size, new_size),
+                None => {
+                    let (plus, minus) = scale(stat, max_change, bar_width);
+                    self.bar(plus, minus)
+                }
+            };
+            let line = format!(
+                " {}{} | {:>w$} {}",
+                name,
+                padding,
+                count,
+                graph,
+                w = count_width
+            );
+            // A file without changed lines has no bar
+            writeln!(out, "{}", line.trim_end())?;
+        }
+
+        let insertions: usize = stats.iter().map(|s| s.insertions).sum();
+        let deletions: usize = stats.iter().map(|s| s.deletions).sum();
+        let mut summary = format!(
+            " {} file{} changed",
+            stats.len(),
+            if stats.len() == 1 { "" } else { "s" }
+        );
+        if insertions > 0 || deletions == 0 {
+            summary += &format!(
+                ", {} insertion{}(+)",
+                insertions,
+                if insertions == 1 { "" } else { "s" }
+            );
+        }
+        if deletions > 0 || insertions == 0 {
+            summary += &format!(
+                ", {} deletion{}(-)",
+                deletions,
+                if deletions == 1 { "" } else { "s" }
+            );
+        }
+        writeln!(out, "{}", summary)
+    }
+
+    fn bar(&self, plus: usize, minus: usize) -> String {
+        let (plus, minus) = ("+".repeat(plus), "-".repeat(minus));
+        if !self.color {
+            return plus + &minus;
+        }
+        let paint = |color: Color, text: String| {
+            if text.is_empty() {
+                text
+            } else {
+                color.paint(text).to_string()
+            }
+        };
+        paint(Green, plus) + &paint(Red, minus)
+    }
+}
+
+/// Prints the statistics as tab separated insertions, deletions and name, binary files
+/// have `-` as counts
+pub fn write_numstat<W: Write>(out: &mut W, stats: &[FileStat]) -> io::Result<()> {
+    for stat in stats {
+        match stat.binary {
+            Some(_) => writeln!(out, "-\t-\t{}", stat.name)?,
+            None => writeln!(
+                out,
+                "{}\t{}\t{}",
+                stat.insertions, stat.deletions, stat.name
+            )?,
+        }
+    }
+    Ok(())
+}
+
+/// Scales the number of insertions and deletions to the bar width like git does, every
+/// non-zero count gets at least one character
+fn scale(stat: &FileStat, max_change: usize, bar_width: usize) -> (usize, usize) {
+    let (insertions, deletions) = (stat.insertions, stat.deletions);
+    if max_change <= bar_width {
+        return (insertions, deletions);
+    }
+    let scale_linear = |n: usize| match n {
+        0 => 0,
+        n => 1 + n * (bar_width - 1) / max_change,
+    };
+    let mut total = scale_linear(insertions + deletions);
+    if total < 2 && insertions > 0 && deletions > 0 {
+        total = 2;
+    }
+    if insertions < deletions {
+        let plus = scale_linear(insertions);
+        (plus, total - plus)
+    } else {
+        let minus = scale_linear(deletions);
+        (total - minus, minus)
+    }
+}
+
+/// Shortens the name from the left to the width, the cut is marked with `...`
+fn shorten(name: &str, width: usize) -> String {
+    if name.width() <= width {
+        return name.to_string();
+    }
+    let mut used = 3;
+    let mut tail: Vec<char> = Vec::new();
+    for c in name.chars().rev() {
+        let w = c.width().unwrap_or(0);
+        if used + w > width {
+            break;
+        }
+        used += w;
+        tail.push(c);
+    }
+    format!("...{}", tail.into_iter().rev().collect::<String>())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diff::hunk::group_hunks;
+    use crate::diff::myers;
+
+    fn stats() -> Vec<FileStat> {
+        let ops = myers::diff(&["a", "b", "c"], &["a", "x", "c", "d"]);
+        vec![
+            FileStat::from_hunks("src/a.rs".to_string(), &group_hunks(&ops, 3)),
+            FileStat::binary("logo.png".to_string(), 10, 12),
+        ]
+    }
+
+    #[test]
+    fn stat_has_bars_and_summary() {
+        let mut out = Vec::new();
+        StatPrinter::new(false)
+            .write_stat(&mut out, &stats())
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            " src/a.rs |   3 ++-
+ logo.png | Bin 10 -> 12 bytes
+ 2 files changed, 2 insertions(+), 1 deletion(-)
+"
+        );
+    }
+
+    #[test]
+    fn numstat_is_tab_separated() {
+        let mut out = Vec::new();
+        write_numstat(&mut out, &stats()).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "2\t1\tsrc/a.rs\n-\t-\tlogo.png\n"
+        );
+    }
+
+    #[test]
+    fn bars_are_scaled() {
+        let stat = |insertions, deletions| FileStat {
+            name: String::new(),
+            insertions,
+            deletions,
+            binary: None,
+        };
+        assert_eq!(scale(&stat(100, 0), 100, 10), (10, 0));
+        // Both kinds of change stay visible
+        assert_eq!(scale(&stat(1, 1), 1000, 10), (1, 1));
+    }
+
+    #[test]
+    fn long_names_are_shortened() {
+        assert_eq!(shorten("abcdefghijkl", 8), "...hijkl");
+        assert_eq!(shorten("abc", 8), "abc");
+    }
+}
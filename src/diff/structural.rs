@@ -0,0 +1,265 @@
+//! Structural diff of JSON, TOML and YAML documents.
+//!
+//! Both documents are parsed into the same tree, so key order, formatting and comments
+//! do not matter. Arrays are aligned by the diff algorithm, so an inserted element is
+//! reported once instead of changing every element after it.
+
+use super::algorithm::Algorithm;
+use super::ops::DiffOp;
+use super::paint;
+use ansi_term::Color::*;
+use serde_json::{Map, Number, Value};
+use std::io::{self, Write};
+use std::path::Path;
+
+/// A difference between two documents at a path like `servers[2].port`
+#[derive(Clone, Debug, PartialEq)]
+pub enum Change {
+    Added(String, Value),
+    Removed(String, Value),
+    Changed(String, Value, Value),
+}
+
+/// The formats which are tried, in this order
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Format {
+    Json,
+    Toml,
+    Yaml,
+}
+
+/// Parses the document as JSON, TOML or YAML. The extension decides the format if it is
+/// known, otherwise all formats are tried. Returns `None` if it is none of them.
+pub fn parse(path: &Path, text: &str) -> Option<Value> {
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default();
+    let formats: &[Format] = match extension.to_ascii_lowercase().as_str() {
+        "json" => &[Format::Json],
+        "toml" => &[Format::Toml],
+        "yaml" | "yml" => &[Format::Yaml],
+        _ => &[Format::Json, Format::Toml, Format::Yaml],
+    };
+    formats.iter().find_map(|format| match format {
+        Format::Json => serde_json::from_str(text).ok(),
+        Format::Toml => text.parse::<toml::Table>().ok().map(from_toml_table),
+        Format::Yaml => {
+            let value = serde_yaml_ng::from_str(text).ok().map(from_yaml)?;
+            // Almost any text is a valid YAML scalar, only accept collections unless
+            // the extension says it is YAML
+            match value {
+                Value::Object(_) | Value::Array(_) => Some(value),
+                _ if formats.len() == 1 => Some(value),
+                _ => None,
+            }
+        }
+    })
+}
+
+fn from_toml_table(table: toml::Table) -> Value {
+    Value::Object(table.into_iter().map(|(k, v)| (k, from_toml(v))).collect())
+}
+
+fn from_toml(value: toml::Value) -> Value {
+    match value {
+        toml::Value::String(s) => Value::String(s),
+        toml::Value::Integer(i) => Value::from(i),
+        toml::Value::Float(f) => Number::from_f64(f).map_or(Value::Null, Value::Number),
+        toml::Value::Boolean(b) => Value::Bool(b),
+        toml::Value::Datetime(d) => Value::String(d.to_string()),
+        toml::Value::Array(a) => Value::Array(a.into_iter().map(from_toml).collect()),
+        toml::Value::Table(t) => from_toml_table(t),
+    }
+}
+
+fn from_yaml(value: serde_yaml_ng::Value) -> Value {
+    match value {
+        serde_yaml_ng::Value::Null => Value::Null,
+        serde_yaml_ng::Value::Bool(b) => Value::Bool(b),
+        serde_yaml_ng::Value::Number(n) => {
+            if let Some(i) = n.as_i64() {
+                Value::from(i)
+            } else if let Some(u) = n.as_u64() {
+                Value::from(u)
+            } else {
+                n.as_f64()
+                    .and_then(Number::from_f64)
+                    .map_or(Value::Null, Value::Number)
+            }
+        }
+        serde_yaml_ng::Value::String(s) => Value::String(s),
+        serde_yaml_ng::Value::Sequence(s) => Value::Array(s.into_iter().map(from_yaml).collect()),
+        serde_yaml_ng::Value::Mapping(m) => {
+            let mut map = Map::new();
+            for (k, v) in m {
+                let key = match from_yaml(k) {
+                    Value::String(s) => s,
+                    other => other.to_string(),
+                };
+                map.insert(key, from_yaml(v));
+            }
+            Value::Object(map)
+        }
+        serde_yaml_ng::Value::Tagged(t) => from_yaml(t.value),
+    }
+}
+
+/// Returns the differences of the trees in path order
+pub fn compare(old: &Value, new: &Value) -> Vec<Change> {
+    let mut changes = Vec::new();
+    compare_at(String::new(), old, new, &mut changes);
+    changes
+}
+
+fn compare_at(path: String, old: &Value, new: &Value, changes: &mut Vec<Change>) {
+    match (old, new) {
+        (Value::Object(old), Value::Object(new)) => {
+            // Both maps are sorted by key
+            let mut keys: Vec<&String> = old.keys().chain(new.keys()).collect();
+            keys.sort();
+            keys.dedup();
+            for key in keys {
+                let path = key_path(&path, key);
+                match (old.get(key), new.get(key)) {
+                    (Some(o), Some(n)) => compare_at(path, o, n, changes),
+                    (Some(o), None) => changes.push(Change::Removed(path, o.clone())),
+                    (None, Some(n)) => changes.push(Change::Added(path, n.clone())),
+                    (None, None) => {}
+                }
+            }
+        }
+        (Value::Array(old), Value::Array(new)) => {
+            let old_keys: Vec<String> = old.iter().map(Value::to_string).collect();
+            let new_keys: Vec<String> = new.iter().map(Value::to_string).collect();
+            for op in Algorithm::default().diff(&old_keys, &new_keys) {
+                match op {
+                    DiffOp::Equal { .. } => {}
+                    DiffOp::Delete { .. } => {
+                        for i in op.old_range() {
+                            changes.push(Change::Removed(index_path(&path, i), old[i].clone()));
+                        }
+                    }
+                    DiffOp::Insert { .. } => {
+                        for j in op.new_range() {
+                            changes.push(Change::Added(index_path(&path, j), new[j].clone()));
+                        }
+                    }
+                    DiffOp::Replace { .. } => {
+                        let (old_range, new_range) = (op.old_range(), op.new_range());
+                        let paired = old_range.len().min(new_range.len());
+                        for (i, j) in old_range.clone().zip(new_range.clone()) {
+                            compare_at(index_path(&path, j), &old[i], &new[j], changes);
+                        }
+                        for i in old_range.skip(paired) {
+                            changes.push(Change::Removed(index_path(&path, i), old[i].clone()));
+                        }
+                        for j in new_range.skip(paired) {
+                            changes.push(Change::Added(index_path(&path, j), new[j].clone()));
+                        }
+                    }
+                }
+            }
+        }
+        _ if old != new => changes.push(Change::Changed(path, old.clone(), new.clone())),
+        _ => {}
+    }
+}
+
+/// Appends a key, keys which are not identifiers are quoted like `["a key"]`
+fn key_path(path: &str, key: &str) -> String {
+    let is_identifier = !key.is_empty()
+        && !key.starts_with(|c: char| c.is_ascii_digit())
+        && key
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '_' || c == '-');
+    match (is_identifier, path.is_empty()) {
+        (true, true) => key.to_string(),
+        (true, false) => format!("{}.{}", path, key),
+        (false, _) => format!("{}[{}]", path, Value::from(key)),
+    }
+}
+
+fn index_path(path: &str, index: usize) -> String {
+    format!("{}[{}]", path, index)
+}
+
+/// Prints one line per changed value with the path to it in the document, like
+/// `~ server.port: 80 -> 8080`
+pub fn write_changes<W: Write>(out: &mut W, changes: &[Change], color: bool) -> io::Result<()> {
+    for change in changes {
+        let display_path = |path: &str| match path {
+            "" => ".".to_string(),
+            path => path.to_string(),
+        };
+        let (style, line) = match change {
+            Change::Added(path, value) => (
+                Green.normal(),
+                format!("+ {}: {}", display_path(path), value),
+            ),
+            Change::Removed(path, value) => {
+                (Red.normal(), format!("- {}: {}", display_path(path), value))
+            }
+            Change::Changed(path, old, new) => (
+                Yellow.normal(),
+                format!("~ {}: {} -> {}", display_path(path), old, new),
+            ),
+        };
+        writeln!(out, "{}", paint(color, style, line))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn changes(old: &str, new: &str) -> String {
+        let path = Path::new("config.json");
+        let (old, new) = (parse(path, old).unwrap(), parse(path, new).unwrap());
+        let mut out = Vec::new();
+        write_changes(&mut out, &compare(&old, &new), false).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn formats_parse_into_the_same_tree() {
+        let json = parse(Path::new("a.json"), r#"{"name": "x", "ports": [80, 443]}"#);
+        let toml = parse(Path::new("a.toml"), "ports = [80, 443]\nname = 'x'\n");
+        let yaml = parse(Path::new("a.yml"), "name: x\nports:\n  - 80\n  - 443\n");
+        assert!(json.is_some());
+        assert_eq!(json, toml);
+        assert_eq!(json, yaml);
+        // Without a known extension, every format is tried
+        assert_eq!(parse(Path::new("a"), "name: x\nports: [80, 443]\n"), json);
+        assert_eq!(parse(Path::new("a"), "just some text"), None);
+    }
+
+    #[test]
+    fn changes_have_paths() {
+        assert_eq!(
+            changes(
+                r#"{"server": {"port": 80}, "old": true, "a key": 1}"#,
+                r#"{"server": {"port": 8080}, "new": null, "a key": 2}"#
+            ),
+            "\
+~ [\"a key\"]: 1 -> 2
++ new: null
+- old: true
+~ server.port: 80 -> 8080
+"
+        );
+        assert_eq!(changes("1", "2"), "~ .: 1 -> 2\n");
+    }
+
+    #[test]
+    fn array_elements_are_aligned() {
+        assert_eq!(
+            changes(r#"[1, 2, 3, {"a": 1}]"#, r#"[0, 1, 2, 3, {"a": 2}]"#),
+            "\
++ [0]: 0
+~ [4].a: 1 -> 2
+"
+        );
+    }
+}
@@ -0,0 +1,198 @@
+//! Syntax highlighting of the unified output, `show diff --syntax`.
+//!
+//! The syntaxes and the theme are the ones bundled with syntect, nothing is loaded at
+//! runtime. A file is highlighted from its first line, because the state of the parser
+//! depends on the lines before, and only up to the last line a hunk shows.
+
+use super::inline::Highlights;
+use ansi_term::{ANSIString, ANSIStrings, Color, Style};
+use std::ops::Range;
+use std::path::Path;
+use std::sync::OnceLock;
+use syntect::easy::HighlightLines;
+use syntect::highlighting::{Theme, ThemeSet};
+use syntect::parsing::{SyntaxReference, SyntaxSet};
+
+/// A bundled theme which is readable on dark terminals
+const THEME: &str = "base16-ocean.dark";
+
+/// The backgrounds of deleted and inserted lines, and of their changed characters
+const DELETED: Color = Color::RGB(63, 0, 1);
+const DELETED_EMPHASIS: Color = Color::RGB(144, 16, 17);
+const INSERTED: Color = Color::RGB(0, 40, 0);
+const INSERTED_EMPHASIS: Color = Color::RGB(0, 96, 0);
+
+static SYNTAXES: OnceLock<SyntaxSet> = OnceLock::new();
+static THEMES: OnceLock<ThemeSet> = OnceLock::new();
+
+/// The foreground colors of the parts of a line, by byte ranges
+pub type LineColors = Vec<(Color, Range<usize>)>;
+
+/// How a line of the diff is shown
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum LineKind {
+    Context,
+    Deleted,
+    Inserted,
+}
+
+/// The syntax colors of the lines of both files
+pub struct SyntaxColors {
+    old: Vec<LineColors>,
+    new: Vec<LineColors>,
+}
+
+impl SyntaxColors {
+    /// Highlights the lines of both files up to `old_end` and `new_end`. Returns `None`
+    /// if the language of the files is not known.
+    pub fn new(
+        path: &Path,
+        old_lines: &[&str],
+        new_lines: &[&str],
+        old_end: usize,
+        new_end: usize,
+    ) -> Option<Self> {
+        let syntaxes = SYNTAXES.get_or_init(SyntaxSet::load_defaults_newlines);
+        let syntax = find_syntax(syntaxes, path)?;
+        let theme = &THEMES.get_or_init(ThemeSet::load_defaults).themes[THEME];
+        Some(Self {
+            old: highlight(syntaxes, syntax, theme, &old_lines[..old_end]),
+            new: highlight(syntaxes, syntax, theme, &new_lines[..new_end]),
+        })
+    }
+
+    #[inline]
+    pub fn old_line(&self, index: usize) -> Option<&LineColors> {
+        self.old.get(index)
+    }
+
+    #[inline]
+    pub fn new_line(&self, index: usize) -> Option<&LineColors> {
+        self.new.get(index)
+    }
+}
+
+fn find_syntax<'a>(syntaxes: &'a SyntaxSet, path: &Path) -> Option<&'a SyntaxReference> {
+    let extension = path.extension().and_then(|e| e.to_str());
+    let name = path.file_name().and_then(|n| n.to_str());
+    // Files like `Makefile` have no extension, their names are listed as extensions
+    extension
+        .and_then(|e| syntaxes.find_syntax_by_extension(e))
+        .or_else(|| name.and_then(|n| syntaxes.find_syntax_by_extension(n)))
+        .filter(|syntax| syntax.name != "Plain Text")
+}
+
+/// Returns the colors of each line, the lines after a line which fails to parse are
+/// left out and shown without syntax colors
+fn highlight(
+    syntaxes: &SyntaxSet,
+    syntax: &SyntaxReference,
+    theme: &Theme,
+    lines: &[&str],
+) -> Vec<LineColors> {
+    let mut highlighter = HighlightLines::new(syntax, theme);
+    let mut colors = Vec::with_capacity(lines.len());
+    for line in lines {
+        let regions = match highlighter.highlight_line(line, syntaxes) {
+            Ok(regions) => regions,
+            Err(_) => break,
+        };
+        let mut start = 0;
+        let mut line_colors = LineColors::with_capacity(regions.len());
+        for (style, text) in regions {
+            let fg = style.foreground;
+            line_colors.push((Color::RGB(fg.r, fg.g, fg.b), start..start + text.len()));
+            start += text.len();
+        }
+        colors.push(line_colors);
+    }
+    colors
+}
+
+/// Paints the content of a line in its syntax colors. Deleted and inserted lines get a
+/// red or green background, the `highlights` a stronger one.
+pub fn paint_line(
+    content: &str,
+    colors: &LineColors,
+    kind: LineKind,
+    highlights: Option<&Highlights>,
+) -> String {
+    let (background, emphasis) = match kind {
+        LineKind::Context => (None, None),
+        LineKind::Deleted => (Some(DELETED), Some(DELETED_EMPHASIS)),
+        LineKind::Inserted => (Some(INSERTED), Some(INSERTED_EMPHASIS)),
+    };
+    let style = |fg: Color, emphasized: bool| {
+        let style = Style::new().fg(fg);
+        match if emphasized { emphasis } else { background } {
+            Some(bg) => style.on(bg),
+            None => style,
+        }
+    };
+    let highlights: &[Range<usize>] = highlights.map_or(&[], |h| h.as_slice());
+
+    let mut painted: Vec<ANSIString> = Vec::new();
+    for (fg, range) in colors {
+        let (begin, end) = (range.start.min(content.len()), range.end.min(content.len()));
+        let mut start = begin;
+        // Splits the part of the line where highlights begin or end
+        for h in highlights.iter().filter(|h| h.start < end && h.end > begin) {
+            if h.start > start {
+                painted.push(style(*fg, false).paint(&content[start..h.start]));
+                start = h.start;
+            }
+            let stop = h.end.min(end);
+            painted.push(style(*fg, true).paint(&content[start..stop]));
+            start = stop;
+        }
+        if start < end {
+            painted.push(style(*fg, false).paint(&content[start..end]));
+        }
+    }
+    ANSIStrings(&painted).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ansi_term::Color::{Blue, Red};
+
+    #[test]
+    fn highlights_split_the_colored_parts() {
+        let colors = vec![(Red, 0..3), (Blue, 3..5)];
+        let highlights = vec![1..2, 3..5];
+        let painted = paint_line("let x", &colors, LineKind::Inserted, Some(&highlights));
+        let style = |fg: Color, bg: Color| Style::new().fg(fg).on(bg);
+        let expected = [
+            style(Red, INSERTED).paint("l"),
+            style(Red, INSERTED_EMPHASIS).paint("e"),
+            style(Red, INSERTED).paint("t"),
+            style(Blue, INSERTED_EMPHASIS).paint(" x"),
+        ];
+        assert_eq!(painted, ANSIStrings(&expected).to_string());
+        // Context lines have no background
+        assert_eq!(
+            paint_line("let", &colors, LineKind::Context, None),
+            Style::new().fg(Red).paint("let").to_string()
+        );
+    }
+
+    #[test]
+    fn lines_are_colored_up_to_the_end() {
+        let lines = ["fn main() {\n", "    let x = 1;\n", "}\n"];
+        let colors = SyntaxColors::new(Path::new("a.rs"), &lines, &lines, 2, 3).unwrap();
+        let line = colors.old_line(1).unwrap();
+        // The parts cover the whole line, without gaps
+        assert_eq!(line.first().map(|(_, r)| r.start), Some(0));
+        assert_eq!(line.last().map(|(_, r)| r.end), Some(lines[1].len()));
+        assert!(line.windows(2).all(|w| w[0].1.end == w[1].1.start));
+        assert!(colors.old_line(2).is_none() && colors.new_line(2).is_some());
+    }
+
+    #[test]
+    fn languages_are_found_by_name() {
+        assert!(SyntaxColors::new(Path::new("Makefile"), &[], &[], 0, 0).is_some());
+        assert!(SyntaxColors::new(Path::new("notes.txt"), &[], &[], 0, 0).is_none());
+        assert!(SyntaxColors::new(Path::new("data.unknown"), &[], &[], 0, 0).is_none());
+    }
+}
@@ -0,0 +1,248 @@
+//! Diff of CSV and TSV tables, rows are matched by their key columns instead of their
+//! position, so sorting a table does not change every row.
+//!
+//! The first row holds the column names. Columns are matched by name, so moved columns
+//! are no difference either.
+
+use super::paint;
+use ansi_term::Color::*;
+use std::collections::{HashMap, VecDeque};
+use std::io::{self, Write};
+
+/// A table with named columns
+pub struct Table {
+    headers: Vec<String>,
+    rows: Vec<Vec<String>>,
+}
+
+impl Table {
+    /// Reads a table whose first row holds the column names
+    pub fn read(bytes: &[u8], delimiter: u8) -> Result<Table, csv::Error> {
+        let mut reader = csv::ReaderBuilder::new()
+            .delimiter(delimiter)
+            .flexible(true)
+            .from_reader(bytes);
+        let headers = reader.headers()?.iter().map(str::to_string).collect();
+        let mut rows = Vec::new();
+        for record in reader.records() {
+            rows.push(record?.iter().map(str::to_string).collect());
+        }
+        Ok(Table { headers, rows })
+    }
+
+    fn column(&self, name: &str) -> Option<usize> {
+        self.headers.iter().position(|h| h == name)
+    }
+
+    /// Returns the cell, missing cells of short rows are empty
+    fn cell<'a>(&self, row: &'a [String], column: usize) -> &'a str {
+        row.get(column).map_or("", String::as_str)
+    }
+}
+
+/// A difference between two tables, rows are named by their key values
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TableChange {
+    ColumnAdded(String),
+    ColumnRemoved(String),
+    /// The key of the row and its other cells
+    RowAdded(Vec<(String, String)>, Vec<(String, String)>),
+    RowRemoved(Vec<(String, String)>, Vec<(String, String)>),
+    /// The key of the row and the changed cells with their old and new values
+    RowChanged(Vec<(String, String)>, Vec<(String, String, String)>),
+}
+
+/// Compares the rows with equal keys. Rows with the same key are paired in the order
+/// they appear. Without key columns the first column is the key.
+pub fn compare_tables(
+    old: &Table,
+    new: &Table,
+    keys: &[String],
+) -> Result<Vec<TableChange>, String> {
+    let keys: Vec<String> = match keys {
+        [] => old.headers.first().cloned().into_iter().collect(),
+        keys => keys.to_vec(),
+    };
+    let key_columns = |table: &Table, side: &str| -> Result<Vec<usize>, String> {
+        keys.iter()
+            .map(|k| {
+                table
+                    .column(k)
+                    .ok_or_else(|| format!("Key column {} is missing in the {} table", k, side))
+            })
+            .collect()
+    };
+    let old_keys = key_columns(old, "old")?;
+    let new_keys = key_columns(new, "new")?;
+    let row_key = |table: &Table, columns: &[usize], row: &[String]| -> Vec<String> {
+        columns
+            .iter()
+            .map(|&c| table.cell(row, c).to_string())
+            .collect()
+    };
+    let named = |values: Vec<String>| -> Vec<(String, String)> {
+        keys.iter().cloned().zip(values).collect()
+    };
+    let other_cells = |table: &Table, row: &[String]| -> Vec<(String, String)> {
+        table
+            .headers
+            .iter()
+            .enumerate()
+            .filter(|(_, name)| !keys.contains(name))
+            .map(|(c, name)| (name.clone(), table.cell(row, c).to_string()))
+            .collect()
+    };
+
+    let mut changes = Vec::new();
+    for name in old.headers.iter().filter(|h| new.column(h).is_none()) {
+        changes.push(TableChange::ColumnRemoved(name.clone()));
+    }
+    for name in new.headers.iter().filter(|h| old.column(h).is_none()) {
+        changes.push(TableChange::ColumnAdded(name.clone()));
+    }
+
+    // The columns of both tables, except the keys, in the order of the new table
+    let common: Vec<(&String, usize, usize)> = new
+        .headers
+        .iter()
+        .enumerate()
+        .filter(|(_, name)| !keys.contains(name))
+        .filter_map(|(j, name)| Some((name, old.column(name)?, j)))
+        .collect();
+
+    let mut new_rows: HashMap<Vec<String>, VecDeque<usize>> = HashMap::new();
+    for (j, row) in new.rows.iter().enumerate() {
+        new_rows
+            .entry(row_key(new, &new_keys, row))
+            .or_default()
+            .push_back(j);
+    }
+
+    let mut matched = vec![false; new.rows.len()];
+    for row in &old.rows {
+        let key = row_key(old, &old_keys, row);
+        let j = match new_rows.get_mut(&key).and_then(VecDeque::pop_front) {
+            Some(j) => j,
+            None => {
+                changes.push(TableChange::RowRemoved(named(key), other_cells(old, row)));
+                continue;
+            }
+        };
+        matched[j] = true;
+        let cells: Vec<(String, String, String)> = common
+            .iter()
+            .filter_map(|&(name, i, j2)| {
+                let (o, n) = (old.cell(row, i), new.cell(&new.rows[j], j2));
+                (o != n).then(|| (name.clone(), o.to_string(), n.to_string()))
+            })
+            .collect();
+        if !cells.is_empty() {
+            changes.push(TableChange::RowChanged(named(key), cells));
+        }
+    }
+    for (row, _) in new.rows.iter().zip(&matched).filter(|(_, m)| !**m) {
+        let key = named(row_key(new, &new_keys, row));
+        changes.push(TableChange::RowAdded(key, other_cells(new, row)));
+    }
+    Ok(changes)
+}
+
+/// Prints the changes one per line, rows are named by their key columns and a changed
+/// row lists only its changed cells
+pub fn write_changes<W: Write>(
+    out: &mut W,
+    changes: &[TableChange],
+    color: bool,
+) -> io::Result<()> {
+    let key = |key: &[(String, String)]| -> String {
+        key.iter()
+            .map(|(name, value)| format!("{}={}", name, value))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    let cells = |cells: &[(String, String)]| -> String {
+        cells
+            .iter()
+            .map(|(name, value)| format!("{}: {:?}", name, value))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    for change in changes {
+        let (style, line) = match change {
+            TableChange::ColumnAdded(name) => (Green.normal(), format!("+ column {}", name)),
+            TableChange::ColumnRemoved(name) => (Red.normal(), format!("- column {}", name)),
+            TableChange::RowAdded(k, c) => {
+                (Green.normal(), format!("+ row {}: {}", key(k), cells(c)))
+            }
+            TableChange::RowRemoved(k, c) => {
+                (Red.normal(), format!("- row {}: {}", key(k), cells(c)))
+            }
+            TableChange::RowChanged(k, changed) => {
+                let changed: Vec<String> = changed
+                    .iter()
+                    .map(|(name, old, new)| format!("{}: {:?} -> {:?}", name, old, new))
+                    .collect();
+                (
+                    Yellow.normal(),
+                    format!("~ row {}: {}", key(k), changed.join(", ")),
+                )
+            }
+        };
+        writeln!(out, "{}", paint(color, style, line))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn changes(old: &str, new: &str, keys: &[&str]) -> Result<String, String> {
+        let read = |text: &str| Table::read(text.as_bytes(), b',').unwrap();
+        let keys: Vec<String> = keys.iter().map(|k| k.to_string()).collect();
+        let changes = compare_tables(&read(old), &read(new), &keys)?;
+        let mut out = Vec::new();
+        write_changes(&mut out, &changes, false).unwrap();
+        Ok(String::from_utf8(out).unwrap())
+    }
+
+    #[test]
+    fn rows_are_matched_by_key() {
+        let old = "id,name,age\n1,ann,30\n2,bob,40\n3,cy,50\n";
+        let new = "id,age,name,city\n3,50,cy,x\n2,41,bob,y\n4,20,dan,z\n";
+        assert_eq!(
+            changes(old, new, &[]).unwrap(),
+            "\
++ column city
+- row id=1: name: \"ann\", age: \"30\"
+~ row id=2: age: \"40\" -> \"41\"
++ row id=4: age: \"20\", name: \"dan\", city: \"z\"
+"
+        );
+    }
+
+    #[test]
+    fn rows_with_equal_keys_are_paired_in_order() {
+        let old = "first,last,n\na,b,1\na,b,2\na,c,3\n";
+        let new = "first,last,n\na,c,3\na,b,1\na,b,5\n";
+        assert_eq!(
+            changes(old, new, &["first", "last"]).unwrap(),
+            "~ row first=a, last=b: n: \"2\" -> \"5\"\n"
+        );
+    }
+
+    #[test]
+    fn missing_key_column_is_an_error() {
+        assert_eq!(
+            changes("id,n\n", "n\n", &["id"]),
+            Err("Key column id is missing in the new table".to_string())
+        );
+    }
+
+    #[test]
+    fn short_rows_have_empty_cells() {
+        let table = Table::read(b"a\tb\n1\n", b'\t').unwrap();
+        assert_eq!(table.headers, ["a", "b"]);
+        assert_eq!(table.cell(&table.rows[0], 1), "");
+    }
+}
@@ -0,0 +1,312 @@
+//! Unified diff output, the format of `diff -u` and `git diff`.
+//!
+//! Each hunk starts with a `@@` header of its line ranges, followed by its lines marked
+//! ` ` for context, `-` for deleted and `+` for inserted. With colors, paired changed
+//! lines can highlight the characters which changed and moved lines get their own
+//! colors.
+
+use super::encoding::Encoding;
+use super::funcname::FunctionMatcher;
+use super::hunk::Hunk;
+use super::inline::{changed_ranges, paired_lines, Highlights};
+use super::moved::MovedLines;
+use super::ops::DiffOp;
+use super::paint;
+use super::syntax::{self, LineColors, LineKind, SyntaxColors};
+use ansi_term::{ANSIStrings, Color, Color::*, Style};
+use std::io::{self, Write};
+use std::ops::Range;
+
+/// Prints hunks in the unified format, which `patch` understands
+pub struct UnifiedPrinter {
+    color: bool,
+    inline_highlight: bool,
+    moved: Option<MovedLines>,
+    functions: Option<FunctionMatcher>,
+    syntax: Option<SyntaxColors>,
+    /// The encodings of the old and the new file
+    encodings: (Encoding, Encoding),
+}
+
+impl UnifiedPrinter {
+    pub fn new(color: bool) -> Self {
+        Self {
+            color,
+            inline_highlight: false,
+            moved: None,
+            functions: None,
+            syntax: None,
+            encodings: (Encoding::Utf8, Encoding::Utf8),
+        }
+    }
+
+    /// Writes the lines in the encodings of their files when colors are off, so that a
+    /// patch of Latin-1 files has their bytes and applies to them. Unicode text is
+    /// written in UTF-8.
+    pub fn with_encodings(mut self, old: Encoding, new: Encoding) -> Self {
+        self.encodings = (old, new);
+        self
+    }
+
+    /// Shows the line of the function or section a hunk is in after its `@@` header, like
+    /// `diff -p` does
+    pub fn with_functions(mut self, functions: FunctionMatcher) -> Self {
+        self.functions = Some(functions);
+        self
+    }
+
+    /// Paints moved lines in their own colors, only takes effect with colors
+    pub fn with_moved(mut self, moved: MovedLines) -> Self {
+        self.moved = Some(moved);
+        self
+    }
+
+    /// Paints the lines in the colors of their language, only takes effect with colors
+    pub fn with_syntax(mut self, syntax: SyntaxColors) -> Self {
+        self.syntax = Some(syntax);
+        self
+    }
+
+    /// Returns the syntax colors of a line, moved lines keep their own colors
+    fn syntax_colors(&self, kind: LineKind, index: usize) -> Option<&LineColors> {
+        let syntax = self.syntax.as_ref().filter(|_| self.color)?;
+        let moved = self.moved.as_ref();
+        match kind {
+            LineKind::Context => syntax.old_line(index),
+            LineKind::Deleted if !moved.is_some_and(|m| m.is_old_moved(index)) => {
+                syntax.old_line(index)
+            }
+            LineKind::Inserted if !moved.is_some_and(|m| m.is_new_moved(index)) => {
+                syntax.new_line(index)
+            }
+            _ => None,
+        }
+    }
+
+    #[inline]
+    fn old_style(&self, index: usize) -> Style {
+        match &self.moved {
+            Some(moved) if moved.is_old_moved(index) => Purple.bold(),
+            _ => Red.normal(),
+        }
+    }
+
+    #[inline]
+    fn new_style(&self, index: usize) -> Style {
+        match &self.moved {
+            Some(moved) if moved.is_new_moved(index) => Cyan.bold(),
+            _ => Green.normal(),
+        }
+    }
+
+    /// Highlights the changed characters of paired lines, only takes effect with colors
+    pub fn with_inline_highlight(mut self, inline_highlight: bool) -> Self {
+        self.inline_highlight = inline_highlight;
+        self
+    }
+
+    pub fn write_header<W: Write>(
+        &self,
+        out: &mut W,
+        old_name: &str,
+        new_name: &str,
+    ) -> io::Result<()> {
+        let bold = Style::new().bold();
+        writeln!(
+            out,
+            "{}",
+            paint(self.color, bold, format!("--- {}", old_name))
+        )?;
+        writeln!(
+            out,
+            "{}",
+            paint(self.color, bold, format!("+++ {}", new_name))
+        )
+    }
+
+    pub fn write_hunk<W: Write>(
+        &self,
+        out: &mut W,
+        hunk: &Hunk,
+        old_lines: &[&str],
+        new_lines: &[&str],
+    ) -> io::Result<()> {
+        let mut header = format!(
+            "@@ -{} +{} @@",
+            format_range(hunk.old_range()),
+            format_range(hunk.new_range())
+        );
+        let function = self
+            .functions
+            .as_ref()
+            .and_then(|f| f.find(old_lines, hunk.old_range().start));
+        if let Some(function) = function {
+            header = format!("{} {}", header, function);
+        }
+        writeln!(out, "{}", paint(self.color, Cyan.normal(), &header))?;
+
+        for op in hunk.ops() {
+            match *op {
+                DiffOp::Equal { .. } => {
+                    for i in op.old_range() {
+                        self.write_op_line(out, LineKind::Context, i, old_lines[i], None)?;
+                    }
+                }
+                DiffOp::Replace { .. } if self.color && self.inline_highlight => {
+                    self.write_replace(out, op, old_lines, new_lines)?;
+                }
+                _ => {
+                    for i in op.old_range() {
+                        self.write_op_line(out, LineKind::Deleted, i, old_lines[i], None)?;
+                    }
+                    for j in op.new_range() {
+                        self.write_op_line(out, LineKind::Inserted, j, new_lines[j], None)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes the line at `index` of the old file, or of the new file if it is inserted
+    fn write_op_line<W: Write>(
+        &self,
+        out: &mut W,
+        kind: LineKind,
+        index: usize,
+        line: &str,
+        highlights: Option<Highlights>,
+    ) -> io::Result<()> {
+        let (marker, color, style) = match kind {
+            LineKind::Context => (' ', None, Style::new()),
+            LineKind::Deleted => ('-', Some(Red), self.old_style(index)),
+            LineKind::Inserted => ('+', Some(Green), self.new_style(index)),
+        };
+        if !self.color {
+            let encoding = match kind {
+                LineKind::Inserted => self.encodings.1,
+                _ => self.encodings.0,
+            };
+            return write_encoded_line(out, marker, line, encoding);
+        }
+        if let Some(colors) = self.syntax_colors(kind, index) {
+            let content = line.strip_suffix('\n').unwrap_or(line);
+            let marker = color
+                .map_or(Style::new(), Style::from)
+                .paint(marker.to_string());
+            let content = syntax::paint_line(content, colors, kind, highlights.as_ref());
+            writeln!(out, "{}{}", marker, content)?;
+            if !line.ends_with('\n') {
+                writeln!(out, "\\ No newline at end of file")?;
+            }
+            return Ok(());
+        }
+        match (highlights, color) {
+            (Some(h), Some(color)) => self.write_highlighted_line(out, marker, color, line, h),
+            _ => self.write_line(out, marker, style, line),
+        }
+    }
+
+    fn write_line<W: Write>(
+        &self,
+        out: &mut W,
+        marker: char,
+        style: Style,
+        line: &str,
+    ) -> io::Result<()> {
+        let content = line.strip_suffix('\n').unwrap_or(line);
+        writeln!(
+            out,
+            "{}",
+            paint(self.color, style, format!("{}{}", marker, content))
+        )?;
+        if !line.ends_with('\n') {
+            writeln!(out, "\\ No newline at end of file")?;
+        }
+        Ok(())
+    }
+
+    /// Writes a replacement, the paired lines are compared character by character
+    fn write_replace<W: Write>(
+        &self,
+        out: &mut W,
+        op: &DiffOp,
+        old_lines: &[&str],
+        new_lines: &[&str],
+    ) -> io::Result<()> {
+        let (old_range, new_range) = (op.old_range(), op.new_range());
+        let mut old_highlights = vec![None; old_range.len()];
+        let mut new_highlights = vec![None; new_range.len()];
+        // Moved lines keep their own colors instead of being highlighted
+        let is_moved = |i: usize, j: usize| {
+            self.moved
+                .as_ref()
+                .is_some_and(|moved| moved.is_old_moved(i) || moved.is_new_moved(j))
+        };
+        for (i, j) in paired_lines(op).filter(|&(i, j)| !is_moved(i, j)) {
+            if let Some((o, n)) = changed_ranges(old_lines[i], new_lines[j]) {
+                old_highlights[i - old_range.start] = Some(o);
+                new_highlights[j - new_range.start] = Some(n);
+            }
+        }
+
+        for (i, highlights) in old_range.zip(old_highlights) {
+            self.write_op_line(out, LineKind::Deleted, i, old_lines[i], highlights)?;
+        }
+        for (j, highlights) in new_range.zip(new_highlights) {
+            self.write_op_line(out, LineKind::Inserted, j, new_lines[j], highlights)?;
+        }
+        Ok(())
+    }
+
+    /// Writes a changed line, the highlighted parts are painted in reverse video
+    fn write_highlighted_line<W: Write>(
+        &self,
+        out: &mut W,
+        marker: char,
+        color: Color,
+        line: &str,
+        highlights: Highlights,
+    ) -> io::Result<()> {
+        let content = line.strip_suffix('\n').unwrap_or(line);
+        let mut painted = vec![color.paint(marker.to_string())];
+        let mut last = 0;
+        for range in highlights {
+            painted.push(color.paint(&content[last..range.start]));
+            painted.push(color.reverse().paint(&content[range.clone()]));
+            last = range.end;
+        }
+        painted.push(color.paint(&content[last..]));
+        writeln!(out, "{}", ANSIStrings(&painted))?;
+        if !line.ends_with('\n') {
+            writeln!(out, "\\ No newline at end of file")?;
+        }
+        Ok(())
+    }
+}
+
+/// Writes an uncolored line in the encoding of its file
+fn write_encoded_line<W: Write>(
+    out: &mut W,
+    marker: char,
+    line: &str,
+    encoding: Encoding,
+) -> io::Result<()> {
+    let content = line.strip_suffix('\n').unwrap_or(line);
+    write!(out, "{}", marker)?;
+    out.write_all(&encoding.encode(content))?;
+    writeln!(out)?;
+    if !line.ends_with('\n') {
+        writeln!(out, "\\ No newline at end of file")?;
+    }
+    Ok(())
+}
+
+/// Formats a 0-based range as the 1-based `start,len` pair of a hunk header
+pub fn format_range(range: Range<usize>) -> String {
+    match range.len() {
+        0 => format!("{},0", range.start),
+        1 => format!("{}", range.start + 1),
+        len => format!("{},{}", range.start + 1, len),
+    }
+}
@@ -0,0 +1,223 @@
+//! Word diff, the changes of a hunk are shown inline like `git diff --word-diff` does.
+//!
+//! The text is split at Unicode word boundaries, so words, punctuation and runs of
+//! white space are separate tokens.
+
+use super::algorithm::Algorithm;
+use super::funcname::FunctionMatcher;
+use super::hunk::Hunk;
+use super::ops::DiffOp;
+use super::paint;
+use super::unified::format_range;
+use ansi_term::{Color, Color::*};
+use std::io::{self, Write};
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Prints the hunks as the new text with the deleted and inserted words marked
+pub struct WordPrinter {
+    color: bool,
+    algorithm: Algorithm,
+    functions: Option<FunctionMatcher>,
+}
+
+impl WordPrinter {
+    pub fn new(color: bool) -> Self {
+        Self {
+            color,
+            algorithm: Algorithm::default(),
+            functions: None,
+        }
+    }
+
+    /// Sets the algorithm which compares the words
+    pub fn with_algorithm(mut self, algorithm: Algorithm) -> Self {
+        self.algorithm = algorithm;
+        self
+    }
+
+    /// Adds the line of the function or section a hunk of words is in to its header
+    pub fn with_functions(mut self, functions: FunctionMatcher) -> Self {
+        self.functions = Some(functions);
+        self
+    }
+
+    pub fn write_hunk<W: Write>(
+        &self,
+        out: &mut W,
+        hunk: &Hunk,
+        old_lines: &[&str],
+        new_lines: &[&str],
+    ) -> io::Result<()> {
+        let mut header = format!(
+            "@@ -{} +{} @@",
+            format_range(hunk.old_range()),
+            format_range(hunk.new_range())
+        );
+        let function = self
+            .functions
+            .as_ref()
+            .and_then(|f| f.find(old_lines, hunk.old_range().start));
+        if let Some(function) = function {
+            header = format!("{} {}", header, function);
+        }
+        writeln!(out, "{}", paint(self.color, Cyan.normal(), header))?;
+
+        let old_text = old_lines[hunk.old_range()].concat();
+        let new_text = new_lines[hunk.new_range()].concat();
+        let old_words: Vec<&str> = old_text.split_word_bounds().collect();
+        let new_words: Vec<&str> = new_text.split_word_bounds().collect();
+
+        let mut ops = self.algorithm.diff(&old_words, &new_words);
+        align_to_lines(&mut ops, &old_words, &new_words);
+
+        let mut text = String::with_capacity(old_text.len() + new_text.len());
+        for op in ops {
+            if let DiffOp::Equal { .. } = op {
+                text.push_str(&old_words[op.old_range()].concat());
+                continue;
+            }
+            self.mark(
+                &mut text,
+                &old_words[op.old_range()].concat(),
+                "[-",
+                "-]",
+                Red,
+            );
+            self.mark(
+                &mut text,
+                &new_words[op.new_range()].concat(),
+                "{+",
+                "+}",
+                Green,
+            );
+        }
+        if !text.ends_with('\n') {
+            text.push('\n');
+        }
+        out.write_all(text.as_bytes())
+    }
+
+    /// Appends the changed words, the marks are closed at every line end
+    fn mark(&self, text: &mut String, words: &str, open: &str, close: &str, color: Color) {
+        for line in words.split_inclusive('\n') {
+            let (content, newline) = match line.strip_suffix('\n') {
+                Some(content) => (content, "\n"),
+                None => (line, ""),
+            };
+            if !content.is_empty() {
+                if self.color {
+                    text.push_str(&color.paint(content).to_string());
+                } else {
+                    text.push_str(open);
+                    text.push_str(content);
+                    text.push_str(close);
+                }
+            }
+            text.push_str(newline);
+        }
+    }
+}
+
+/// Slides insertions and deletions between equal words so that they end at a line end
+/// if they can, then added and removed lines are marked as a whole.
+fn align_to_lines(ops: &mut [DiffOp], old: &[&str], new: &[&str]) {
+    for n in 1..ops.len().saturating_sub(1) {
+        let (words, range) = match ops[n] {
+            DiffOp::Insert { .. } => (new, ops[n].new_range()),
+            DiffOp::Delete { .. } => (old, ops[n].old_range()),
+            _ => continue,
+        };
+        let (before, after) = match (ops[n - 1], ops[n + 1]) {
+            (DiffOp::Equal { len: before, .. }, DiffOp::Equal { len: after, .. }) => {
+                (before, after)
+            }
+            _ => continue,
+        };
+
+        // The run can move as far as the words before or after it repeat the run
+        let up = (1..=before)
+            .take_while(|&k| words[range.start - k] == words[range.end - k])
+            .count() as isize;
+        let down = (0..after)
+            .take_while(|&k| words[range.start + k] == words[range.end + k])
+            .count() as isize;
+        let shift = match (-up..=down)
+            .rev()
+            .find(|&shift| words[(range.end as isize + shift - 1) as usize] == "\n")
+        {
+            Some(shift) if shift != 0 => shift,
+            _ => continue,
+        };
+
+        let moved = |index: usize| (index as isize + shift) as usize;
+        if let DiffOp::Equal { len, .. } = &mut ops[n - 1] {
+            *len = moved(*len);
+        }
+        ops[n] = match ops[n] {
+            DiffOp::Insert {
+                old_index,
+                new_index,
+                new_len,
+            } => DiffOp::Insert {
+                old_index: moved(old_index),
+                new_index: moved(new_index),
+                new_len,
+            },
+            DiffOp::Delete {
+                old_index,
+                old_len,
+                new_index,
+            } => DiffOp::Delete {
+                old_index: moved(old_index),
+                old_len,
+                new_index: moved(new_index),
+            },
+            op => op,
+        };
+        if let DiffOp::Equal {
+            old_index,
+            new_index,
+            len,
+        } = &mut ops[n + 1]
+        {
+            *old_index = moved(*old_index);
+            *new_index = moved(*new_index);
+            *len = (*len as isize - shift) as usize;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diff::hunk::group_hunks;
+
+    fn words(old: &[&str], new: &[&str]) -> String {
+        let printer = WordPrinter::new(false);
+        let mut out = Vec::new();
+        for hunk in group_hunks(&Algorithm::default().diff(old, new), 3) {
+            printer.write_hunk(&mut out, &hunk, old, new).unwrap();
+        }
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn changed_words_are_marked_inline() {
+        assert_eq!(
+            words(&["the quick brown fox\n"], &["the slow brown fox!\n"]),
+            "@@ -1 +1 @@\nthe [-quick-]{+slow+} brown fox{+!+}\n"
+        );
+    }
+
+    #[test]
+    fn added_lines_are_marked_as_a_whole() {
+        assert_eq!(
+            words(&["a\n", "c\n"], &["a\n", "b\n", "c\n"]),
+            "@@ -1,2 +1,3 @@\na\n{+b+}\nc\n"
+        );
+        assert_eq!(
+            words(&["a\n", "b b\n", "c\n"], &["a\n", "c\n"]),
+            "@@ -1,3 +1,2 @@\na\n[-b b-]\nc\n"
+        );
+    }
+}
@@ -129,10 +129,10 @@ pub fn run_location() {
     }
     println!("cnt = {}", cnt);
     return;
-    let mut v1 = walk1(&dir);
-    let mut v2 = walk2(&dir);
-    let mut v3 = walk3(&dir);
-    let mut v4 = walk4(&dir);
+    let mut v1 = walk1(dir);
+    let mut v2 = walk2(dir);
+    let mut v3 = walk3(dir);
+    let mut v4 = walk4(dir);
     if v1 == v3 {
         println!("YES");
     } else {
@@ -1,44 +1,57 @@
 mod completion;
 mod count;
 mod diff;
+#[allow(unused)]
 mod location;
+mod merge;
+mod patch;
 mod path;
 mod utils;
+#[allow(dead_code)]
 mod walk;
 mod which;
 
 use completion::*;
 use diff::*;
+use merge::*;
+use patch::*;
 use path::*;
 use utils::*;
 use which::*;
 
-use crate::location::run_location;
 use ansi_term::Color::*;
 use clap::App;
 use fern::colors::ColoredLevelConfig;
+use std::env;
+use std::io::{self, IsTerminal};
+use std::process;
 
+/// Logs to stderr, so messages never mix with the output of a command. Colors are only
+/// used if stderr is a terminal.
 fn setup_logger() {
+    let color = io::stderr().is_terminal();
     fern::Dispatch::new()
-        .format(|out, message, record| {
-            out.finish(format_args!(
-                "\x1b[{}m{}: {}\x1b[0m",
-                ColoredLevelConfig::new()
-                    .get_color(&record.level())
-                    .to_fg_str(),
-                record.level().to_string().to_lowercase(),
-                message
-            ))
+        .format(move |out, message, record| {
+            let level = record.level().to_string().to_lowercase();
+            if color {
+                out.finish(format_args!(
+                    "\x1b[{}m{}: {}\x1b[0m",
+                    ColoredLevelConfig::new()
+                        .get_color(&record.level())
+                        .to_fg_str(),
+                    level,
+                    message
+                ))
+            } else {
+                out.finish(format_args!("{}: {}", level, message))
+            }
         })
-        .level(log::LevelFilter::Debug)
-        .chain(std::io::stdout())
+        .level(log::LevelFilter::Info)
+        .level_for("globset", log::LevelFilter::Warn)
+        .level_for("ignore", log::LevelFilter::Warn)
+        .chain(io::stderr())
         .apply()
-        .unwrap_or_else(|e| {
-            println!(
-                "{}",
-                Red.paint(format!("Setup logger failed: {}.", e.to_string()))
-            )
-        });
+        .unwrap_or_else(|e| eprintln!("{}", Red.paint(format!("Setup logger failed: {}.", e))));
 }
 
 pub fn build_app() -> App<'static, 'static> {
@@ -49,16 +62,40 @@ pub fn build_app() -> App<'static, 'static> {
         .subcommand(WhichCommand::util_sub_command())
         .subcommand(PathCommand::util_sub_command())
         .subcommand(DiffCommand::util_sub_command())
+        .subcommand(PatchCommand::util_sub_command())
+        .subcommand(MergeCommand::util_sub_command())
         .subcommand(CompletionCommand::util_sub_command())
 }
 
+/// Returns the exit code of a command line error. The commands whose exit code 1 is a
+/// result exit with their code for trouble instead.
+fn usage_error_code() -> i32 {
+    match env::args().nth(1).as_deref() {
+        Some("diff") => diff::EXIT_TROUBLE,
+        Some("patch") => patch::EXIT_TROUBLE,
+        Some("merge") => merge::EXIT_TROUBLE,
+        _ => 1,
+    }
+}
+
 fn main() {
-    let matches = build_app().get_matches();
+    setup_logger();
+    let matches = match build_app().get_matches_safe() {
+        Ok(matches) => matches,
+        // The help and the version are printed to stdout and exit with 0
+        Err(e) if !e.use_stderr() => e.exit(),
+        Err(e) => {
+            eprintln!("{}", e.message);
+            process::exit(usage_error_code());
+        }
+    };
 
     match matches.subcommand() {
         ("which", Some(m)) => WhichCommand::run(m),
         ("path", Some(m)) => PathCommand::run(m),
         ("diff", Some(m)) => DiffCommand::run(m),
+        ("patch", Some(m)) => PatchCommand::run(m),
+        ("merge", Some(m)) => MergeCommand::run(m),
         ("generate-completions", Some(m)) => CompletionCommand::run(m),
         _ => {}
     }
@@ -0,0 +1,496 @@
+use crate::diff::algorithm::Algorithm;
+use crate::diff::input::{split_lines, Input};
+use crate::diff::ops::DiffOp;
+use crate::utils::UtilSubCommand;
+use clap::{App, Arg, ArgMatches, SubCommand};
+use log::error;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::ops::Range;
+
+/// The exit code used when the merge could not be done at all
+pub const EXIT_TROUBLE: i32 = 255;
+
+/// The highest number of conflicts which is reported in the exit code
+const MAX_REPORTED_CONFLICTS: usize = 127;
+
+pub struct MergeCommand;
+
+impl UtilSubCommand for MergeCommand {
+    fn util_sub_command<'a, 'b>() -> App<'a, 'b> {
+        SubCommand::with_name("merge")
+            .about("Merge the changes of two files made to a common base")
+            .version("1.0")
+            .arg(
+                Arg::with_name("style")
+                    .long("style")
+                    .value_name("STYLE")
+                    .takes_value(true)
+                    .possible_values(&["diff3", "zdiff3"])
+                    .help("The style of conflict markers (default diff3)"),
+            )
+            .arg(
+                Arg::with_name("ours")
+                    .long("ours")
+                    .conflicts_with_all(&["theirs", "union"])
+                    .help("Resolve conflicts by taking the left side"),
+            )
+            .arg(
+                Arg::with_name("theirs")
+                    .long("theirs")
+                    .conflicts_with("union")
+                    .help("Resolve conflicts by taking the right side"),
+            )
+            .arg(
+                Arg::with_name("union")
+                    .long("union")
+                    .help("Resolve conflicts by taking the left side followed by the right side"),
+            )
+            .arg(
+                Arg::with_name("algorithm")
+                    .long("algorithm")
+                    .value_name("ALGORITHM")
+                    .takes_value(true)
+                    .possible_values(Algorithm::NAMES)
+                    .help("The diff algorithm to use (default myers)"),
+            )
+            .arg(
+                Arg::with_name("output")
+                    .long("out")
+                    .short("o")
+                    .value_name("FILE")
+                    .takes_value(true)
+                    .help("Write the merged file to FILE instead of stdout"),
+            )
+            .arg(
+                Arg::with_name("base")
+                    .value_name("BASE")
+                    .required(true)
+                    .help("The common base of both files"),
+            )
+            .arg(
+                Arg::with_name("left")
+                    .value_name("LEFT")
+                    .required(true)
+                    .help("Our version of the file"),
+            )
+            .arg(
+                Arg::with_name("right")
+                    .value_name("RIGHT")
+                    .required(true)
+                    .help("Their version of the file"),
+            )
+    }
+
+    /// Exits with the number of conflicts which are left in the merged file
+    #[inline]
+    fn run(args: &ArgMatches) {
+        let code = match run_merge(args) {
+            Ok(conflicts) => conflicts.min(MAX_REPORTED_CONFLICTS) as i32,
+            Err(e) => {
+                error!("{}", e);
+                EXIT_TROUBLE
+            }
+        };
+        std::process::exit(code);
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum ConflictStyle {
+    Diff3,
+    ZealousDiff3,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Resolution {
+    Keep,
+    Ours,
+    Theirs,
+    Union,
+}
+
+/// A part of the merge, the ranges index the base, left and right lines
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Chunk {
+    Stable(Range<usize>),
+    Unstable {
+        base: Range<usize>,
+        left: Range<usize>,
+        right: Range<usize>,
+    },
+}
+
+/// Returns for each base line the index of the line it is matched to
+fn matches(ops: &[DiffOp], base_len: usize) -> Vec<Option<usize>> {
+    let mut matched = vec![None; base_len];
+    for op in ops {
+        if let DiffOp::Equal {
+            old_index,
+            new_index,
+            len,
+        } = *op
+        {
+            for n in 0..len {
+                matched[old_index + n] = Some(new_index + n);
+            }
+        }
+    }
+    matched
+}
+
+/// Splits the three files into chunks where all of them agree and chunks where at least
+/// one side changed the base, as described in "A Formal Investigation of Diff3".
+fn chunks(
+    base_len: usize,
+    left_len: usize,
+    right_len: usize,
+    left_ops: &[DiffOp],
+    right_ops: &[DiffOp],
+) -> Vec<Chunk> {
+    let left_match = matches(left_ops, base_len);
+    let right_match = matches(right_ops, base_len);
+    let (mut i, mut l, mut r) = (0, 0, 0);
+    let mut chunks = Vec::new();
+
+    while i < base_len || l < left_len || r < right_len {
+        let stable = (i..base_len)
+            .take_while(|&k| left_match[k] == Some(l + k - i) && right_match[k] == Some(r + k - i))
+            .count();
+        if stable > 0 {
+            chunks.push(Chunk::Stable(i..i + stable));
+            i += stable;
+            l += stable;
+            r += stable;
+            continue;
+        }
+
+        let next = (i..base_len).find(|&k| left_match[k].is_some() && right_match[k].is_some());
+        let (base_end, left_end, right_end) = match next {
+            Some(k) => (
+                k,
+                left_match[k].unwrap_or(left_len),
+                right_match[k].unwrap_or(right_len),
+            ),
+            None => (base_len, left_len, right_len),
+        };
+        chunks.push(Chunk::Unstable {
+            base: i..base_end,
+            left: l..left_end,
+            right: r..right_end,
+        });
+        i = base_end;
+        l = left_end;
+        r = right_end;
+    }
+    chunks
+}
+
+struct Merger<'a> {
+    base: &'a [&'a str],
+    left: &'a [&'a str],
+    right: &'a [&'a str],
+    left_label: String,
+    base_label: String,
+    right_label: String,
+    style: ConflictStyle,
+    resolution: Resolution,
+}
+
+impl Merger<'_> {
+    /// Writes the merged file and returns the number of conflicts written
+    fn write<W: Write>(&self, out: &mut W, chunks: &[Chunk]) -> io::Result<usize> {
+        let mut conflicts = 0;
+        for chunk in chunks {
+            let (base, left, right) = match chunk {
+                Chunk::Stable(range) => {
+                    write_lines(out, &self.base[range.clone()])?;
+                    continue;
+                }
+                Chunk::Unstable { base, left, right } => (
+                    &self.base[base.clone()],
+                    &self.left[left.clone()],
+                    &self.right[right.clone()],
+                ),
+            };
+
+            if left == base || left == right {
+                write_lines(out, right)?;
+            } else if right == base {
+                write_lines(out, left)?;
+            } else {
+                match self.resolution {
+                    Resolution::Ours => write_lines(out, left)?,
+                    Resolution::Theirs => write_lines(out, right)?,
+                    Resolution::Union => {
+                        write_lines(out, left)?;
+                        if !right.is_empty() {
+                            end_line(out, left)?;
+                        }
+                        write_lines(out, right)?;
+                    }
+                    Resolution::Keep => {
+                        self.write_conflict(out, base, left, right)?;
+                        conflicts += 1;
+                    }
+                }
+            }
+        }
+        Ok(conflicts)
+    }
+
+    fn write_conflict<W: Write>(
+        &self,
+        out: &mut W,
+        base: &[&str],
+        left: &[&str],
+        right: &[&str],
+    ) -> io::Result<()> {
+        // zdiff3 moves the lines both sides agree on out of the conflict
+        let (prefix, suffix) = match self.style {
+            ConflictStyle::Diff3 => (0, 0),
+            ConflictStyle::ZealousDiff3 => {
+                let prefix = left.iter().zip(right).take_while(|(a, b)| a == b).count();
+                let suffix = left[prefix..]
+                    .iter()
+                    .rev()
+                    .zip(right[prefix..].iter().rev())
+                    .take_while(|(a, b)| a == b)
+                    .count();
+                (prefix, suffix)
+            }
+        };
+
+        let (ours, theirs) = (
+            &left[prefix..left.len() - suffix],
+            &right[prefix..right.len() - suffix],
+        );
+        write_lines(out, &left[..prefix])?;
+        end_line(out, &left[..prefix])?;
+        writeln!(out, "<<<<<<< {}", self.left_label)?;
+        write_lines(out, ours)?;
+        end_line(out, ours)?;
+        writeln!(out, "||||||| {}", self.base_label)?;
+        write_lines(out, base)?;
+        end_line(out, base)?;
+        writeln!(out, "=======")?;
+        write_lines(out, theirs)?;
+        end_line(out, theirs)?;
+        writeln!(out, ">>>>>>> {}", self.right_label)?;
+        write_lines(out, &left[left.len() - suffix..])
+    }
+}
+
+/// Writes the lines as they are
+fn write_lines<W: Write>(out: &mut W, lines: &[&str]) -> io::Result<()> {
+    lines
+        .iter()
+        .try_for_each(|line| out.write_all(line.as_bytes()))
+}
+
+/// Adds the newline which the last of the written lines misses, so that a conflict
+/// marker which follows starts on its own line
+fn end_line<W: Write>(out: &mut W, written: &[&str]) -> io::Result<()> {
+    match written.last() {
+        Some(line) if !line.ends_with('\n') => out.write_all(b"\n"),
+        _ => Ok(()),
+    }
+}
+
+/// Merges the files and returns the number of conflicts which are left
+fn run_merge(args: &ArgMatches) -> io::Result<usize> {
+    let load = |name: &str| {
+        let path = args.value_of(name).unwrap_or_default();
+        Input::from_path(path).map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path, e)))
+    };
+    let (base, left, right) = (load("base")?, load("left")?, load("right")?);
+    let (base_text, left_text, right_text) = (base.text(), left.text(), right.text());
+    let base_lines = split_lines(&base_text);
+    let left_lines = split_lines(&left_text);
+    let right_lines = split_lines(&right_text);
+
+    let algorithm: Algorithm = args
+        .value_of("algorithm")
+        .and_then(|a| a.parse().ok())
+        .unwrap_or_default();
+    let left_ops = algorithm.diff(&base_lines, &left_lines);
+    let right_ops = algorithm.diff(&base_lines, &right_lines);
+    let chunks = chunks(
+        base_lines.len(),
+        left_lines.len(),
+        right_lines.len(),
+        &left_ops,
+        &right_ops,
+    );
+
+    let label = |input: &Input| input.path().to_string_lossy().into_owned();
+    let merger = Merger {
+        base: &base_lines,
+        left: &left_lines,
+        right: &right_lines,
+        left_label: label(&left),
+        base_label: label(&base),
+        right_label: label(&right),
+        style: match args.value_of("style") {
+            Some("zdiff3") => ConflictStyle::ZealousDiff3,
+            _ => ConflictStyle::Diff3,
+        },
+        resolution: if args.is_present("ours") {
+            Resolution::Ours
+        } else if args.is_present("theirs") {
+            Resolution::Theirs
+        } else if args.is_present("union") {
+            Resolution::Union
+        } else {
+            Resolution::Keep
+        },
+    };
+
+    match args.value_of("output") {
+        Some(f) => {
+            let file =
+                File::create(f).map_err(|e| io::Error::new(e.kind(), format!("{}: {}", f, e)))?;
+            let mut out = BufWriter::new(file);
+            let conflicts = merger.write(&mut out, &chunks)?;
+            out.flush()?;
+            Ok(conflicts)
+        }
+        None => {
+            let stdout = io::stdout();
+            let mut out = stdout.lock();
+            let conflicts = merger.write(&mut out, &chunks)?;
+            out.flush()?;
+            Ok(conflicts)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn merge(base: &str, left: &str, right: &str, style: ConflictStyle) -> (String, usize) {
+        resolve(base, left, right, style, Resolution::Keep)
+    }
+
+    fn resolve(
+        base: &str,
+        left: &str,
+        right: &str,
+        style: ConflictStyle,
+        resolution: Resolution,
+    ) -> (String, usize) {
+        let (base, left, right) = (split_lines(base), split_lines(left), split_lines(right));
+        let algorithm = Algorithm::default();
+        let chunks = chunks(
+            base.len(),
+            left.len(),
+            right.len(),
+            &algorithm.diff(&base, &left),
+            &algorithm.diff(&base, &right),
+        );
+        let merger = Merger {
+            base: &base,
+            left: &left,
+            right: &right,
+            left_label: "left".to_string(),
+            base_label: "base".to_string(),
+            right_label: "right".to_string(),
+            style,
+            resolution,
+        };
+        let mut out = Vec::new();
+        let conflicts = merger.write(&mut out, &chunks).unwrap();
+        (String::from_utf8(out).unwrap(), conflicts)
+    }
+
+    #[test]
+    fn clean_merge_keeps_missing_final_newline() {
+        let (merged, conflicts) = merge("a\nb\nc", "A\nb\nc", "a\nb\nc", ConflictStyle::Diff3);
+        assert_eq!(conflicts, 0);
+        assert_eq!(merged, "A\nb\nc");
+    }
+
+    #[test]
+    fn conflict_markers_start_on_their_own_line() {
+        let (merged, conflicts) = merge("a\nb", "a\nl", "a\nr", ConflictStyle::Diff3);
+        assert_eq!(conflicts, 1);
+        assert_eq!(
+            merged,
+            "a\n<<<<<<< left\nl\n||||||| base\nb\n=======\nr\n>>>>>>> right\n"
+        );
+    }
+
+    #[test]
+    fn chunks_split_at_lines_all_files_agree_on() {
+        let ops = |old: &[&str], new: &[&str]| Algorithm::default().diff(old, new);
+        let base = ["a", "b", "c", "d"];
+        let left = ["a", "B", "c", "d"];
+        let right = ["a", "b", "c", "D", "E"];
+        let chunks = chunks(4, 4, 5, &ops(&base, &left), &ops(&base, &right));
+        assert_eq!(
+            chunks,
+            vec![
+                Chunk::Stable(0..1),
+                Chunk::Unstable {
+                    base: 1..2,
+                    left: 1..2,
+                    right: 1..2
+                },
+                Chunk::Stable(2..3),
+                Chunk::Unstable {
+                    base: 3..4,
+                    left: 3..4,
+                    right: 3..5
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn changes_of_one_side_are_taken() {
+        let (merged, conflicts) = merge(
+            "a\nb\nc\nd\n",
+            "a\nB\nc\nd\n",
+            "a\nb\nc\nD\n",
+            ConflictStyle::Diff3,
+        );
+        assert_eq!((merged.as_str(), conflicts), ("a\nB\nc\nD\n", 0));
+    }
+
+    #[test]
+    fn zdiff3_moves_common_lines_out_of_the_conflict() {
+        let (base, left, right) = ("a\nb\nc\n", "a\nx\nl\nz\nc\n", "a\nx\nr\nz\nc\n");
+        let (merged, _) = merge(base, left, right, ConflictStyle::Diff3);
+        assert_eq!(
+            merged,
+            "a\n<<<<<<< left\nx\nl\nz\n||||||| base\nb\n=======\nx\nr\nz\n>>>>>>> right\nc\n"
+        );
+        let (merged, conflicts) = merge(base, left, right, ConflictStyle::ZealousDiff3);
+        assert_eq!(conflicts, 1);
+        assert_eq!(
+            merged,
+            "a\nx\n<<<<<<< left\nl\n||||||| base\nb\n=======\nr\n>>>>>>> right\nz\nc\n"
+        );
+    }
+
+    #[test]
+    fn conflict_before_a_last_line_without_newline() {
+        let (merged, conflicts) = merge("a\nb\nc", "a\nl\nc", "a\nr\nc", ConflictStyle::Diff3);
+        assert_eq!(conflicts, 1);
+        assert_eq!(
+            merged,
+            "a\n<<<<<<< left\nl\n||||||| base\nb\n=======\nr\n>>>>>>> right\nc"
+        );
+    }
+
+    #[test]
+    fn resolutions_of_conflicts() {
+        let resolve =
+            |resolution| resolve("a\nb", "a\nl", "a\nr", ConflictStyle::Diff3, resolution);
+        assert_eq!(resolve(Resolution::Ours), ("a\nl".to_string(), 0));
+        assert_eq!(resolve(Resolution::Theirs), ("a\nr".to_string(), 0));
+        // Both sides keep their own lines, the missing newline of the left one is added
+        assert_eq!(resolve(Resolution::Union), ("a\nl\nr".to_string(), 0));
+    }
+}
@@ -0,0 +1,691 @@
+use crate::utils::{PathExt, UtilSubCommand};
+use clap::{App, Arg, ArgMatches, SubCommand};
+use log::error;
+use std::env;
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+/// The number of context lines which may be ignored by default when a hunk does not match
+const DEFAULT_FUZZ: usize = 2;
+
+/// The exit code if all hunks were applied
+const EXIT_APPLIED: i32 = 0;
+
+/// The exit code if some hunks failed and were saved to reject files, like patch(1)
+const EXIT_FAILED: i32 = 1;
+
+/// The exit code if the patch could not be read or a file could not be patched
+pub const EXIT_TROUBLE: i32 = 2;
+
+pub struct PatchCommand;
+
+impl UtilSubCommand for PatchCommand {
+    fn util_sub_command<'a, 'b>() -> App<'a, 'b> {
+        SubCommand::with_name("patch")
+            .about("Apply a unified diff to files")
+            .version("1.0")
+            .arg(
+                Arg::with_name("input")
+                    .long("input")
+                    .short("i")
+                    .value_name("PATCHFILE")
+                    .takes_value(true)
+                    .help("Read the patch from this file instead of stdin"),
+            )
+            .arg(
+                Arg::with_name("strip")
+                    .long("strip")
+                    .short("p")
+                    .value_name("NUM")
+                    .takes_value(true)
+                    .validator(is_number)
+                    .help("Strip NUM leading components from file names (default 0)"),
+            )
+            .arg(
+                Arg::with_name("fuzz")
+                    .long("fuzz")
+                    .short("F")
+                    .value_name("NUM")
+                    .takes_value(true)
+                    .validator(is_number)
+                    .help(
+                        "Ignore up to NUM lines of context when a hunk does not match (default 2)",
+                    ),
+            )
+            .arg(
+                Arg::with_name("reverse")
+                    .long("reverse")
+                    .short("R")
+                    .help("Apply the patch in reverse, undoing it"),
+            )
+            .arg(
+                Arg::with_name("dry_run")
+                    .long("dry-run")
+                    .help("Only check whether the patch applies, do not change any file"),
+            )
+            .arg(
+                Arg::with_name("directory")
+                    .long("directory")
+                    .short("d")
+                    .value_name("DIR")
+                    .takes_value(true)
+                    .help("Resolve the file names of the patch relative to DIR"),
+            )
+            .arg(
+                Arg::with_name("file")
+                    .value_name("FILE")
+                    .help("The file to patch, overrides the file names in the patch"),
+            )
+    }
+
+    #[inline]
+    fn run(args: &ArgMatches) {
+        std::process::exit(run_patch(args));
+    }
+}
+
+fn is_number(value: String) -> Result<(), String> {
+    match value.parse::<usize>() {
+        Ok(_) => Ok(()),
+        Err(_) => Err(format!("Invalid number {}", value)),
+    }
+}
+
+/// A line of a hunk. Lines are bytes, so a file in a legacy encoding is patched as it is.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum HunkLine {
+    Context(Vec<u8>),
+    Delete(Vec<u8>),
+    Insert(Vec<u8>),
+}
+
+impl HunkLine {
+    fn text(&self) -> &[u8] {
+        match self {
+            HunkLine::Context(s) | HunkLine::Delete(s) | HunkLine::Insert(s) => s,
+        }
+    }
+
+    fn text_mut(&mut self) -> &mut Vec<u8> {
+        match self {
+            HunkLine::Context(s) | HunkLine::Delete(s) | HunkLine::Insert(s) => s,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct PatchHunk {
+    /// 1-based start line of the old side as given in the header
+    old_start: usize,
+    new_start: usize,
+    lines: Vec<HunkLine>,
+    /// The original text of the hunk, written to the reject file if it fails
+    raw: Vec<u8>,
+}
+
+impl PatchHunk {
+    fn reverse(&mut self) {
+        std::mem::swap(&mut self.old_start, &mut self.new_start);
+        for line in &mut self.lines {
+            *line = match std::mem::replace(line, HunkLine::Context(Vec::new())) {
+                HunkLine::Delete(s) => HunkLine::Insert(s),
+                HunkLine::Insert(s) => HunkLine::Delete(s),
+                context => context,
+            };
+        }
+    }
+
+    /// Returns the lines expected in the file and the lines which replace them, `fuzz`
+    /// leading and trailing context lines are left out.
+    fn sides(&self, fuzz: usize) -> (Vec<&[u8]>, Vec<&[u8]>) {
+        let leading = self
+            .lines
+            .iter()
+            .take_while(|l| matches!(l, HunkLine::Context(_)))
+            .count()
+            .min(fuzz);
+        let trailing = self
+            .lines
+            .iter()
+            .rev()
+            .take_while(|l| matches!(l, HunkLine::Context(_)))
+            .count()
+            .min(fuzz);
+        let lines =
+            &self.lines[leading..self.lines.len() - trailing.min(self.lines.len() - leading)];
+
+        let old = lines
+            .iter()
+            .filter(|l| !matches!(l, HunkLine::Insert(_)))
+            .map(HunkLine::text)
+            .collect();
+        let new = lines
+            .iter()
+            .filter(|l| !matches!(l, HunkLine::Delete(_)))
+            .map(HunkLine::text)
+            .collect();
+        (old, new)
+    }
+
+    fn old_len(&self) -> usize {
+        self.lines
+            .iter()
+            .filter(|l| !matches!(l, HunkLine::Insert(_)))
+            .count()
+    }
+
+    fn leading_context(&self) -> usize {
+        self.lines
+            .iter()
+            .take_while(|l| matches!(l, HunkLine::Context(_)))
+            .count()
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+struct FilePatch {
+    old_path: String,
+    new_path: String,
+    header: Vec<u8>,
+    hunks: Vec<PatchHunk>,
+}
+
+/// Parses the file name of a `---` or `+++` header, a timestamp after a tab is dropped
+fn parse_file_name(header: &[u8]) -> String {
+    let header = String::from_utf8_lossy(&header[4..]);
+    let name = header.split('\t').next().unwrap_or_default();
+    name.trim_end_matches(['\r', '\n']).to_string()
+}
+
+/// Parses a hunk header like `@@ -1,3 +1,4 @@`, returns the start lines and lengths
+fn parse_hunk_header(line: &[u8]) -> Option<(usize, usize, usize, usize)> {
+    // The function name after the ranges may be in any encoding
+    let line = String::from_utf8_lossy(line);
+    let mut parts = line.strip_prefix("@@ ")?.split_whitespace();
+    let range = |part: Option<&str>, sign: char| -> Option<(usize, usize)> {
+        let part = part?.strip_prefix(sign)?;
+        match part.split_once(',') {
+            Some((start, len)) => Some((start.parse().ok()?, len.parse().ok()?)),
+            None => Some((part.parse().ok()?, 1)),
+        }
+    };
+    let (old_start, old_len) = range(parts.next(), '-')?;
+    let (new_start, new_len) = range(parts.next(), '+')?;
+    Some((old_start, old_len, new_start, new_len))
+}
+
+/// Parses all file patches of a unified diff, text outside of hunks is skipped
+fn parse_patch(text: &[u8]) -> Result<Vec<FilePatch>, String> {
+    let lines: Vec<&[u8]> = text.split_inclusive(|&b| b == b'\n').collect();
+    let show = |line: &[u8]| String::from_utf8_lossy(line).trim_end().to_string();
+    let mut patches: Vec<FilePatch> = Vec::new();
+    let mut i = 0;
+
+    while i < lines.len() {
+        let line = lines[i];
+        if line.starts_with(b"--- ") && i + 1 < lines.len() && lines[i + 1].starts_with(b"+++ ") {
+            patches.push(FilePatch {
+                old_path: parse_file_name(line),
+                new_path: parse_file_name(lines[i + 1]),
+                header: [line, lines[i + 1]].concat(),
+                hunks: vec![],
+            });
+            i += 2;
+            continue;
+        }
+
+        if line.starts_with(b"@@ ") {
+            let (old_start, mut old_len, new_start, mut new_len) = parse_hunk_header(line)
+                .ok_or_else(|| {
+                    format!("Malformed hunk header at line {}: {}", i + 1, show(line))
+                })?;
+            let patch = patches
+                .last_mut()
+                .ok_or_else(|| format!("Hunk without file header at line {}", i + 1))?;
+            let mut hunk = PatchHunk {
+                old_start,
+                new_start,
+                lines: vec![],
+                raw: line.to_vec(),
+            };
+            i += 1;
+
+            while i < lines.len() && (old_len > 0 || new_len > 0 || lines[i].starts_with(b"\\")) {
+                let line = lines[i];
+                let text = line.get(1..).unwrap_or(b"\n").to_vec();
+                match line[0] {
+                    b' ' | b'\n' | b'\r' => {
+                        old_len = old_len.saturating_sub(1);
+                        new_len = new_len.saturating_sub(1);
+                        hunk.lines.push(HunkLine::Context(if line[0] == b' ' {
+                            text
+                        } else {
+                            line.to_vec()
+                        }));
+                    }
+                    b'-' => {
+                        old_len = old_len.saturating_sub(1);
+                        hunk.lines.push(HunkLine::Delete(text));
+                    }
+                    b'+' => {
+                        new_len = new_len.saturating_sub(1);
+                        hunk.lines.push(HunkLine::Insert(text));
+                    }
+                    // "\ No newline at end of file" belongs to the previous line
+                    b'\\' => {
+                        if let Some(last) = hunk.lines.last_mut() {
+                            let text = last.text_mut();
+                            if text.ends_with(b"\n") {
+                                text.pop();
+                            }
+                        }
+                    }
+                    _ => return Err(format!("Malformed hunk line {}: {}", i + 1, show(line))),
+                }
+                hunk.raw.extend_from_slice(line);
+                i += 1;
+            }
+            if old_len > 0 || new_len > 0 {
+                return Err(format!("Truncated hunk at line {}", i));
+            }
+            patch.hunks.push(hunk);
+            continue;
+        }
+
+        i += 1;
+    }
+
+    Ok(patches)
+}
+
+/// Removes `strip` leading components from the file name of a patch
+fn strip_components(name: &str, strip: usize) -> PathBuf {
+    Path::new(name).components().skip(strip).collect()
+}
+
+/// Finds where the expected lines are located, positions closer to `expected` are tried
+/// first and no position before `min` is considered.
+fn locate(lines: &[Vec<u8>], old: &[&[u8]], expected: usize, min: usize) -> Option<usize> {
+    let matches_at = |pos: usize| {
+        lines[pos..pos + old.len()]
+            .iter()
+            .zip(old)
+            .all(|(a, b)| a == b)
+    };
+    let max = lines.len().checked_sub(old.len())?;
+    if min > max {
+        return None;
+    }
+    let expected = expected.clamp(min, max);
+    for distance in 0.. {
+        let after = Some(expected + distance).filter(|&p| p <= max);
+        let before = expected
+            .checked_sub(distance)
+            .filter(|&p| p >= min && distance > 0);
+        if after.is_none() && before.is_none() && distance > 0 {
+            break;
+        }
+        if let Some(pos) = after.into_iter().chain(before).find(|&p| matches_at(p)) {
+            return Some(pos);
+        }
+    }
+    None
+}
+
+#[derive(Debug)]
+struct PatchOption {
+    strip: usize,
+    fuzz: usize,
+    reverse: bool,
+    dry_run: bool,
+    base: PathBuf,
+    file: Option<PathBuf>,
+}
+
+/// Applies the hunks of one file, returns the number of failed hunks
+fn apply_file_patch(patch: &FilePatch, option: &PatchOption) -> io::Result<usize> {
+    let (old_name, new_name) = if option.reverse {
+        (&patch.new_path, &patch.old_path)
+    } else {
+        (&patch.old_path, &patch.new_path)
+    };
+    let target = match &option.file {
+        Some(f) => f.to_absolute(&option.base),
+        None => {
+            let old = strip_components(old_name, option.strip).to_absolute(&option.base);
+            let new = strip_components(new_name, option.strip).to_absolute(&option.base);
+            if old_name != "/dev/null" && old.exists() || new_name == "/dev/null" {
+                old
+            } else {
+                new
+            }
+        }
+    };
+
+    let creating = old_name == "/dev/null";
+    let deleting = new_name == "/dev/null";
+    let content = if creating && !target.exists() {
+        Vec::new()
+    } else {
+        fs::read(&target)
+            .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", target.to_string(), e)))?
+    };
+    let mut lines: Vec<Vec<u8>> = content
+        .split_inclusive(|&b| b == b'\n')
+        .map(<[u8]>::to_vec)
+        .collect();
+
+    eprintln!(
+        "{} file {}",
+        if option.dry_run {
+            "checking"
+        } else {
+            "patching"
+        },
+        target.to_string()
+    );
+
+    let rejects = apply_hunks(&mut lines, &patch.hunks, option);
+    if !rejects.is_empty() {
+        let mut reject_path = target.clone().into_os_string();
+        reject_path.push(".rej");
+        let reject_path = PathBuf::from(reject_path);
+        eprintln!(
+            "{} out of {} hunk{} FAILED -- {} rejects to file {}",
+            rejects.len(),
+            patch.hunks.len(),
+            if patch.hunks.len() == 1 { "" } else { "s" },
+            if option.dry_run {
+                "would save"
+            } else {
+                "saving"
+            },
+            reject_path.to_string()
+        );
+        if !option.dry_run {
+            fs::write(&reject_path, reject_text(patch, &rejects))?;
+        }
+    }
+
+    if !option.dry_run {
+        let result = lines.concat();
+        if deleting && result.is_empty() {
+            fs::remove_file(&target)?;
+        } else {
+            if let Some(parent) = target.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(&target, result)?;
+        }
+    }
+
+    Ok(rejects.len())
+}
+
+/// Applies the hunks to the lines of a file, returns the hunks which failed
+fn apply_hunks<'a>(
+    lines: &mut Vec<Vec<u8>>,
+    hunks: &'a [PatchHunk],
+    option: &PatchOption,
+) -> Vec<&'a PatchHunk> {
+    let mut rejects: Vec<&PatchHunk> = Vec::new();
+    let mut shift: isize = 0;
+    let mut min = 0;
+    for (n, hunk) in hunks.iter().enumerate() {
+        let mut hunk = hunk.clone();
+        if option.reverse {
+            hunk.reverse();
+        }
+
+        let mut applied = false;
+        for fuzz in 0..=option.fuzz {
+            let (old, new) = hunk.sides(fuzz);
+            let skipped = hunk.leading_context().min(fuzz);
+            // An empty side starts after the given line instead of at it
+            let start = if hunk.old_len() == 0 {
+                hunk.old_start
+            } else {
+                hunk.old_start.saturating_sub(1)
+            };
+            let expected = (start + skipped) as isize + shift;
+            let expected = expected.max(0) as usize;
+            if let Some(pos) = locate(lines, &old, expected, min) {
+                let offset = pos as isize - expected as isize;
+                let mut notes = String::new();
+                if fuzz > 0 {
+                    notes.push_str(&format!(" with fuzz {}", fuzz));
+                }
+                if offset != 0 {
+                    notes.push_str(&format!(" (offset {} lines)", offset));
+                }
+                if !notes.is_empty() {
+                    let at = pos.saturating_sub(skipped) + 1;
+                    eprintln!("Hunk #{} succeeded at {}{}.", n + 1, at, notes);
+                }
+                lines.splice(pos..pos + old.len(), new.iter().map(|s| s.to_vec()));
+                shift = (pos + new.len()) as isize - (start + skipped + old.len()) as isize;
+                min = pos + new.len();
+                applied = true;
+                break;
+            }
+        }
+
+        if !applied {
+            eprintln!("Hunk #{} FAILED at {}.", n + 1, hunk.old_start);
+            rejects.push(&hunks[n]);
+        }
+    }
+    rejects
+}
+
+/// Returns the content of the reject file, the failed hunks as they were in the patch
+fn reject_text(patch: &FilePatch, rejects: &[&PatchHunk]) -> Vec<u8> {
+    let mut text = patch.header.clone();
+    for hunk in rejects {
+        text.extend_from_slice(&hunk.raw);
+    }
+    text
+}
+
+/// Applies the patch and returns the exit code. The progress is printed to stderr, like
+/// the errors.
+fn run_patch(args: &ArgMatches) -> i32 {
+    let cwd = match env::current_dir() {
+        Ok(p) => p,
+        Err(e) => {
+            error!("Get current directory failed: {}", e);
+            return EXIT_TROUBLE;
+        }
+    };
+    // The values are checked by their validators
+    let parse_number = |name: &str, default: usize| {
+        args.value_of(name)
+            .and_then(|n| n.parse().ok())
+            .unwrap_or(default)
+    };
+    let option = PatchOption {
+        strip: parse_number("strip", 0),
+        fuzz: parse_number("fuzz", DEFAULT_FUZZ),
+        reverse: args.is_present("reverse"),
+        dry_run: args.is_present("dry_run"),
+        base: args
+            .value_of("directory")
+            .map_or(cwd.clone(), |d| Path::new(d).to_absolute(&cwd)),
+        file: args.value_of("file").map(PathBuf::from),
+    };
+
+    let mut text = Vec::new();
+    let read = match args.value_of("input") {
+        Some(f) => fs::read(f)
+            .map(|t| text = t)
+            .map_err(|e| format!("{}: {}", f, e)),
+        None => io::stdin()
+            .read_to_end(&mut text)
+            .map(|_| ())
+            .map_err(|e| format!("Read patch failed: {}", e)),
+    };
+    if let Err(e) = read {
+        error!("{}", e);
+        return EXIT_TROUBLE;
+    }
+
+    let patches = match parse_patch(&text) {
+        Ok(p) if p.is_empty() => {
+            error!("Only garbage was found in the patch input");
+            return EXIT_TROUBLE;
+        }
+        Ok(p) => p,
+        Err(e) => {
+            error!("{}", e);
+            return EXIT_TROUBLE;
+        }
+    };
+
+    let mut code = EXIT_APPLIED;
+    for patch in &patches {
+        match apply_file_patch(patch, &option) {
+            Ok(0) => {}
+            Ok(_) => code = code.max(EXIT_FAILED),
+            Err(e) => {
+                error!("{}", e);
+                code = EXIT_TROUBLE;
+            }
+        }
+    }
+    code
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OLD: &str = "1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n11\n12";
+    const NEW: &str = "1\n2\n3\nfour\n5\n6\n7\n8\n9\n10\n11\ntwelve";
+    const PATCH: &str = "\
+diff -u old new
+--- old\t2024-01-01 00:00:00
++++ new\t2024-01-02 00:00:00
+@@ -1,7 +1,7 @@
+ 1
+ 2
+ 3
+-4
++four
+ 5
+ 6
+ 7
+@@ -9,4 +9,4 @@
+ 9
+ 10
+ 11
+-12
+\\ No newline at end of file
++twelve
+\\ No newline at end of file
+";
+
+    fn option(reverse: bool, fuzz: usize) -> PatchOption {
+        PatchOption {
+            strip: 0,
+            fuzz,
+            reverse,
+            dry_run: true,
+            base: PathBuf::new(),
+            file: None,
+        }
+    }
+
+    /// Applies the hunks of the only file of the patch, returns the text and the number
+    /// of failed hunks
+    fn split_bytes(text: &[u8]) -> Vec<Vec<u8>> {
+        text.split_inclusive(|&b| b == b'\n')
+            .map(<[u8]>::to_vec)
+            .collect()
+    }
+
+    fn split(text: &str) -> Vec<Vec<u8>> {
+        split_bytes(text.as_bytes())
+    }
+
+    fn apply(text: &str, patch: &str, option: &PatchOption) -> (String, usize) {
+        let patches = parse_patch(patch.as_bytes()).unwrap();
+        let mut lines = split(text);
+        let rejects = apply_hunks(&mut lines, &patches[0].hunks, option);
+        (String::from_utf8(lines.concat()).unwrap(), rejects.len())
+    }
+
+    #[test]
+    fn parses_file_names_and_hunks() {
+        let patches = parse_patch(PATCH.as_bytes()).unwrap();
+        assert_eq!(patches.len(), 1);
+        let patch = &patches[0];
+        assert_eq!(
+            (patch.old_path.as_str(), patch.new_path.as_str()),
+            ("old", "new")
+        );
+        assert_eq!(patch.hunks.len(), 2);
+        assert_eq!((patch.hunks[1].old_start, patch.hunks[1].new_start), (9, 9));
+        // The marker removes the newline of the line before it
+        assert_eq!(
+            patch.hunks[1].lines[3..],
+            [
+                HunkLine::Delete(b"12".to_vec()),
+                HunkLine::Insert(b"twelve".to_vec())
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_patches() {
+        assert!(parse_patch(b"@@ -1 +1 @@\n-a\n+b\n").is_err());
+        assert!(parse_patch(b"--- a\n+++ b\n@@ -1,2 +1,2 @@\n-a\n+b\n").is_err());
+        assert!(parse_patch(b"--- a\n+++ b\n@@ -1 +1 @@\n*a\n").is_err());
+        assert_eq!(parse_patch(b"no patch here\n").unwrap().len(), 0);
+    }
+
+    #[test]
+    fn applies_and_reverses() {
+        assert_eq!(apply(OLD, PATCH, &option(false, 0)), (NEW.to_string(), 0));
+        assert_eq!(apply(NEW, PATCH, &option(true, 0)), (OLD.to_string(), 0));
+    }
+
+    #[test]
+    fn applies_at_an_offset() {
+        let old = format!("a\nb\n{}", OLD);
+        let new = format!("a\nb\n{}", NEW);
+        assert_eq!(apply(&old, PATCH, &option(false, 0)), (new, 0));
+    }
+
+    #[test]
+    fn ignores_changed_context_with_fuzz() {
+        let old = OLD.replacen("1\n", "one\n", 1);
+        let new = NEW.replacen("1\n", "one\n", 1);
+        assert_eq!(apply(&old, PATCH, &option(false, 0)).1, 1);
+        assert_eq!(apply(&old, PATCH, &option(false, 2)), (new, 0));
+    }
+
+    #[test]
+    fn rejects_hunks_which_do_not_apply() {
+        let old = OLD.replace("4\n", "vier\n");
+        let patches = parse_patch(PATCH.as_bytes()).unwrap();
+        let mut lines = split(&old);
+        let rejects = apply_hunks(&mut lines, &patches[0].hunks, &option(false, 2));
+        assert_eq!(rejects.len(), 1);
+        // The other hunk is still applied
+        assert!(lines.concat().ends_with(b"11\ntwelve"));
+        let reject = reject_text(&patches[0], &rejects);
+        let hunk = &PATCH[PATCH.find("--- ").unwrap()..PATCH.find("@@ -9").unwrap()];
+        assert_eq!(reject, hunk.as_bytes());
+    }
+
+    #[test]
+    fn applies_to_latin1_files() {
+        let old = b"caf\xe9\nna\xefve\n";
+        let patch = b"--- a\n+++ b\n@@ -1,2 +1,2 @@\n caf\xe9\n-na\xefve\n+na\xeff\n";
+        let patches = parse_patch(patch).unwrap();
+        let mut lines = split_bytes(old);
+        assert!(apply_hunks(&mut lines, &patches[0].hunks, &option(false, 0)).is_empty());
+        assert_eq!(lines.concat(), b"caf\xe9\nna\xeff\n");
+    }
+}
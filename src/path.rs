@@ -2,7 +2,7 @@ use crate::utils::*;
 use clap::{App, Arg, ArgMatches, SubCommand};
 use log::error;
 use std::env;
-use std::path::{Path, PathBuf};
+use std::path::Path;
 
 pub struct PathCommand;
 
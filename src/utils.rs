@@ -13,6 +13,7 @@ pub trait UtilSubCommand {
 }
 
 /// Converts a windows style path name to unix style path name
+#[allow(dead_code)]
 pub trait PathExt {
     fn to_absolute(&self, relative_to: &Path) -> PathBuf;
     fn to_string(&self) -> String;
@@ -132,7 +133,7 @@ impl PathExt for Path {
         {
             use std::os::unix::fs::MetadataExt;
             self.metadata()
-                .map_or(false, |meta| meta.is_file() && meta.mode() & 0o111 != 0)
+                .is_ok_and(|meta| meta.is_file() && meta.mode() & 0o111 != 0)
         }
 
         #[cfg(windows)]
@@ -183,11 +184,7 @@ impl PathExt for Path {
             if let Ok(mut file) = File::open(self) {
                 let mut content = [0; 1024];
                 if let Ok(bytes_read) = file.read(&mut content) {
-                    // Treat PDF format as binary
-                    if bytes_read >= 4 && &content[..4] == b"%PDF" {
-                        return true;
-                    }
-                    return memchr(b'\x00', &content[..bytes_read]).is_some();
+                    return is_binary_content(&content[..bytes_read]);
                 }
             }
         }
@@ -195,11 +192,19 @@ impl PathExt for Path {
     }
 }
 
+/// Returns `true` if the content looks binary, it is a PDF or has a null byte in its
+/// first 1024 bytes
+pub fn is_binary_content(content: &[u8]) -> bool {
+    let head = &content[..content.len().min(1024)];
+    // Treat PDF format as binary
+    head.starts_with(b"%PDF") || memchr(b'\x00', head).is_some()
+}
+
 pub fn print_path(path: &Path) {
     let cwd = match env::current_dir() {
         Ok(p) => p,
         Err(e) => {
-            error!("Get current directory failed: {}", e.to_string());
+            error!("Get current directory failed: {}", e);
             return;
         }
     };
@@ -1,7 +1,10 @@
-use std::collections::BinaryHeap;
-use std::fs::{FileType, Metadata};
+use ignore::overrides::{Override, OverrideBuilder};
+use ignore::WalkBuilder;
+use log::warn;
+use std::fs::{self, FileType, Metadata};
 use std::io;
 use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
 
 pub struct DirEntry {
     path: PathBuf,
@@ -12,12 +15,18 @@ pub struct DirEntry {
 }
 
 impl DirEntry {
+    /// Reads the metadata of the entry. A symbolic link is an entry of its own unless
+    /// `follow_link` is set, a dangling link is one in both cases.
     pub fn from_path<P: AsRef<Path>>(
         path: P,
         depth: usize,
         follow_link: bool,
     ) -> io::Result<DirEntry> {
-        let meta = std::fs::metadata(&path)?;
+        let meta = if follow_link {
+            fs::metadata(&path).or_else(|_| fs::symlink_metadata(&path))?
+        } else {
+            fs::symlink_metadata(&path)?
+        };
         Ok(DirEntry {
             path: PathBuf::from(path.as_ref()),
             typ: meta.file_type(),
@@ -79,8 +88,10 @@ pub enum EntryType {
     Empty,
 }
 
+/// The filters which `Walk::entries` applies. The patterns are globs like in a
+/// `.gitignore` file, the times are seconds since the Unix epoch.
 #[derive(Clone, Debug)]
-struct SearchFilter {
+pub struct SearchFilter {
     follow_symlink: bool,
     skip_hidden: bool,
     ignore_case: bool,
@@ -91,7 +102,7 @@ struct SearchFilter {
     create_before: Option<u64>,
     create_after: Option<u64>,
     change_before: Option<u64>,
-    change_after:Option<u64>,
+    change_after: Option<u64>,
     access_before: Option<u64>,
     access_after: Option<u64>,
 }
@@ -111,13 +122,65 @@ impl Default for SearchFilter {
             change_before: None,
             change_after: None,
             access_before: None,
-            access_after: None
+            access_after: None,
         }
     }
 }
 
+impl SearchFilter {
+    pub fn skip_hidden(mut self, skip_hidden: bool) -> Self {
+        self.skip_hidden = skip_hidden;
+        self
+    }
+
+    /// Only files which match one of the include patterns pass, directories are still
+    /// walked into
+    pub fn include_pattern<S: Into<String>>(mut self, pattern: S) -> Self {
+        self.include_pattern.push(pattern.into());
+        self
+    }
+
+    /// Files and directories which match an exclude pattern do not pass, nothing below
+    /// an excluded directory is walked
+    pub fn exclude_pattern<S: Into<String>>(mut self, pattern: S) -> Self {
+        self.exclude_pattern.push(pattern.into());
+        self
+    }
+
+    /// Builds the include and exclude patterns into overrides relative to `root`
+    fn overrides(&self, root: &Path) -> Result<Override, ignore::Error> {
+        let mut builder = OverrideBuilder::new(root);
+        builder.case_insensitive(self.ignore_case)?;
+        for pattern in &self.include_pattern {
+            builder.add(pattern)?;
+        }
+        for pattern in &self.exclude_pattern {
+            builder.add(&format!("!{}", pattern))?;
+        }
+        builder.build()
+    }
+
+    /// Returns `true` if the times of an entry are within the time filters. A time the
+    /// platform does not record never passes a filter on it.
+    fn in_time(&self, meta: &Metadata) -> bool {
+        let within = |time: io::Result<SystemTime>, before: Option<u64>, after: Option<u64>| {
+            if before.is_none() && after.is_none() {
+                return true;
+            }
+            let secs = match time.map(|t| t.duration_since(UNIX_EPOCH)) {
+                Ok(Ok(duration)) => duration.as_secs(),
+                _ => return false,
+            };
+            before.is_none_or(|before| secs < before) && after.is_none_or(|after| secs > after)
+        };
+        within(meta.created(), self.create_before, self.create_after)
+            && within(meta.modified(), self.change_before, self.change_after)
+            && within(meta.accessed(), self.access_before, self.access_after)
+    }
+}
+
 #[derive(Clone, Debug, Default)]
-struct PrintOption {
+pub struct PrintOption {
     ignore_error: bool,
     no_color: bool,
     type_mask: u8,
@@ -135,10 +198,11 @@ impl PrintOption {
     }
 }
 
-struct Walk {
+pub struct Walk {
     search_paths: Vec<PathBuf>,
     threads: usize,
     filter: SearchFilter,
+    /// How the entries are printed, `entries` does not use it
     option: PrintOption,
 }
 
@@ -152,6 +216,11 @@ impl Walk {
         }
     }
 
+    pub fn add_search_path<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.search_paths.push(PathBuf::from(path.as_ref()));
+        self
+    }
+
     pub fn with_threads(mut self, n: usize) -> Self {
         self.threads = n;
         self
@@ -196,4 +265,61 @@ impl Walk {
         self.filter.max_depth = Some(depth);
         self
     }
+
+    /// Walks through all search paths in file name order and yields the entries which
+    /// pass the filter as they are found, the search paths themselves are not included.
+    /// Siblings are sorted, so the entries come in the order of their paths. The walk runs
+    /// on the calling thread whatever `threads` is, so that the order holds.
+    ///
+    /// The time filters apply to files only, directories are walked into in any case.
+    /// Entries which can not be read are skipped with a warning, and so is a search path
+    /// whose patterns are not valid globs.
+    pub fn entries(self) -> impl Iterator<Item = DirEntry> {
+        let filter = self.filter;
+        self.search_paths
+            .into_iter()
+            .flat_map({
+                let filter = filter.clone();
+                move |search_path| {
+                    let overrides = match filter.overrides(&search_path) {
+                        Ok(overrides) => overrides,
+                        Err(e) => {
+                            warn!("{}: {}", search_path.display(), e);
+                            return None;
+                        }
+                    };
+                    let walk = WalkBuilder::new(&search_path)
+                        .hidden(filter.skip_hidden)
+                        .follow_links(filter.follow_symlink)
+                        .max_depth(filter.max_depth)
+                        .overrides(overrides)
+                        .git_ignore(false)
+                        .sort_by_file_name(|a, b| a.cmp(b))
+                        .build();
+                    Some(walk)
+                }
+            })
+            .flatten()
+            .filter_map(move |result| {
+                let entry = match result {
+                    Ok(entry) => entry,
+                    Err(e) => {
+                        warn!("{}", e);
+                        return None;
+                    }
+                };
+                let depth = entry.depth();
+                if depth == 0 || filter.min_depth.is_some_and(|min| depth < min) {
+                    return None;
+                }
+                let entry = DirEntry::from_path(entry.path(), depth, filter.follow_symlink)
+                    .map_err(|e| warn!("{}: {}", entry.path().display(), e))
+                    .ok()?;
+                if entry.is_dir() || filter.in_time(entry.metadata()) {
+                    Some(entry)
+                } else {
+                    None
+                }
+            })
+    }
 }